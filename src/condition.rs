@@ -0,0 +1,537 @@
+//! Conditions used by scenes to decide whether they can be played, e.g.
+//! `CatDead == 0 and favor(Joe) > 15`.
+//!
+//! - values: integers, `day`, `favor(Name)`, `alive(Name)`, `mission(Type)`
+//!   (number of open missions of that type), `supply(Store)` (days of oxygen, food
+//!   or power left), `flag(Name)` or a bare flag name
+//! - comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=`
+//! - logic: `and`/`&&`, `or`/`||`, `not`/`!` and parentheses
+//!
+//! A value on its own is true when it is not zero.
+
+use serde::Deserialize;
+use std::fmt;
+
+/// What a condition can look at. Unknown names evaluate to `None` and count as 0.
+pub trait ConditionContext {
+    fn flag(&self, name: &str) -> isize;
+    fn favor(&self, character: &str) -> Option<isize>;
+    fn alive(&self, character: &str) -> Option<bool>;
+    fn day(&self) -> isize;
+    fn missions(&self, mission: &str) -> Option<isize>;
+    fn supply(&self, supply: &str) -> Option<isize>;
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Condition {
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Compare(Value, Comparison, Value),
+    Truthy(Value),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(isize),
+    Day,
+    Flag(String),
+    Favor(String),
+    Alive(String),
+    Mission(String),
+    Supply(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Condition, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.or()?;
+        match parser.peek() {
+            None => Ok(condition),
+            Some(token) => Err(format!("unexpected {token:?} in condition `{source}`")),
+        }
+    }
+
+    /// Every value the condition looks at.
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Condition::And(a, b) | Condition::Or(a, b) => {
+                let mut values = a.values();
+                values.extend(b.values());
+                values
+            }
+            Condition::Not(a) => a.values(),
+            Condition::Compare(a, _, b) => vec![a, b],
+            Condition::Truthy(a) => vec![a],
+        }
+    }
+
+    pub fn eval(&self, context: &impl ConditionContext) -> bool {
+        match self {
+            Condition::And(a, b) => a.eval(context) && b.eval(context),
+            Condition::Or(a, b) => a.eval(context) || b.eval(context),
+            Condition::Not(a) => !a.eval(context),
+            Condition::Compare(a, comparison, b) => {
+                let (a, b) = (a.eval(context), b.eval(context));
+                match comparison {
+                    Comparison::Equal => a == b,
+                    Comparison::NotEqual => a != b,
+                    Comparison::Less => a < b,
+                    Comparison::LessEqual => a <= b,
+                    Comparison::Greater => a > b,
+                    Comparison::GreaterEqual => a >= b,
+                }
+            }
+            Condition::Truthy(a) => a.eval(context) != 0,
+        }
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Condition::parse(&source)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // `and` binds tighter than `or` and both group from the left, so an `or` inside
+        // an `and` needs parentheses and so does anything on the right of its own kind
+        let wrapped = |condition: &Condition, wrap: bool| {
+            if wrap {
+                format!("({condition})")
+            } else {
+                condition.to_string()
+            }
+        };
+        match self {
+            Condition::And(a, b) => {
+                let left = wrapped(a, matches!(**a, Condition::Or(..)));
+                let right = wrapped(b, matches!(**b, Condition::And(..) | Condition::Or(..)));
+                write!(f, "{left} and {right}")
+            }
+            Condition::Or(a, b) => {
+                let right = wrapped(b, matches!(**b, Condition::Or(..)));
+                write!(f, "{a} or {right}")
+            }
+            Condition::Not(a) => match **a {
+                Condition::And(..) | Condition::Or(..) => write!(f, "not ({a})"),
+                _ => write!(f, "not {a}"),
+            },
+            Condition::Compare(a, comparison, b) => {
+                let comparison = match comparison {
+                    Comparison::Equal => "==",
+                    Comparison::NotEqual => "!=",
+                    Comparison::Less => "<",
+                    Comparison::LessEqual => "<=",
+                    Comparison::Greater => ">",
+                    Comparison::GreaterEqual => ">=",
+                };
+                write!(f, "{a} {comparison} {b}")
+            }
+            Condition::Truthy(a) => write!(f, "{a}"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Day => write!(f, "day"),
+            Value::Flag(name) => match name.as_str() {
+                "day" | "favor" | "alive" | "mission" | "supply" | "flag" => {
+                    write!(f, "flag({name})")
+                }
+                _ => write!(f, "{name}"),
+            },
+            Value::Favor(name) => write!(f, "favor({name})"),
+            Value::Alive(name) => write!(f, "alive({name})"),
+            Value::Mission(name) => write!(f, "mission({name})"),
+            Value::Supply(name) => write!(f, "supply({name})"),
+        }
+    }
+}
+
+impl Value {
+    pub fn eval(&self, context: &impl ConditionContext) -> isize {
+        match self {
+            Value::Number(n) => *n,
+            Value::Day => context.day(),
+            Value::Flag(name) => context.flag(name),
+            Value::Favor(name) => context.favor(name).unwrap_or(0),
+            Value::Alive(name) => context.alive(name).unwrap_or(false) as isize,
+            Value::Mission(name) => context.missions(name).unwrap_or(0),
+            Value::Supply(name) => context.supply(name).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(isize),
+    Ident(String),
+    Compare(Comparison),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '-' {
+            chars.next();
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, d)) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            let number = source[start..end].parse().map_err(|_| {
+                format!(
+                    "bad number `{}` in condition `{source}`",
+                    &source[start..end]
+                )
+            })?;
+            tokens.push(Token::Number(number));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || d == '_') {
+                    break;
+                }
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            tokens.push(match &source[start..end] {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                ident => Token::Ident(ident.to_string()),
+            });
+            continue;
+        }
+
+        chars.next();
+        let next = chars.peek().map(|&(_, d)| d);
+        let (token, wide) = match (c, next) {
+            ('=', Some('=')) => (Token::Compare(Comparison::Equal), true),
+            ('!', Some('=')) => (Token::Compare(Comparison::NotEqual), true),
+            ('<', Some('=')) => (Token::Compare(Comparison::LessEqual), true),
+            ('>', Some('=')) => (Token::Compare(Comparison::GreaterEqual), true),
+            ('&', Some('&')) => (Token::And, true),
+            ('|', Some('|')) => (Token::Or, true),
+            ('<', _) => (Token::Compare(Comparison::Less), false),
+            ('>', _) => (Token::Compare(Comparison::Greater), false),
+            ('!', _) => (Token::Not, false),
+            ('(', _) => (Token::Open, false),
+            (')', _) => (Token::Close, false),
+            _ => return Err(format!("unexpected `{c}` in condition `{source}`")),
+        };
+        if wide {
+            chars.next();
+        }
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(format!("expected {expected:?}, found {other:?}")),
+        }
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<Condition, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Condition, String> {
+        if self.peek() == Some(&Token::Open) {
+            self.next();
+            let condition = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(condition);
+        }
+
+        let value = self.value()?;
+        if let Some(&Token::Compare(comparison)) = self.peek() {
+            self.next();
+            return Ok(Condition::Compare(value, comparison, self.value()?));
+        }
+        Ok(Condition::Truthy(value))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Value::Number(n)),
+            Some(Token::Ident(ident)) => {
+                let wrap: fn(String) -> Value = match ident.as_str() {
+                    "day" => return Ok(Value::Day),
+                    "favor" => Value::Favor,
+                    "alive" => Value::Alive,
+                    "mission" => Value::Mission,
+                    "supply" => Value::Supply,
+                    "flag" => Value::Flag,
+                    _ => return Ok(Value::Flag(ident)),
+                };
+                self.expect(Token::Open)?;
+                let Some(Token::Ident(name)) = self.next() else {
+                    return Err(format!("expected a name inside {ident}(..)"));
+                };
+                self.expect(Token::Close)?;
+                Ok(wrap(name))
+            }
+            other => Err(format!("expected a value, found {other:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Crew;
+
+    impl ConditionContext for Crew {
+        fn flag(&self, name: &str) -> isize {
+            match name {
+                "CatDead" => 1,
+                "Debt" => -3,
+                "day" => 7,
+                _ => 0,
+            }
+        }
+
+        fn favor(&self, character: &str) -> Option<isize> {
+            (character == "Joe").then_some(20)
+        }
+
+        fn alive(&self, character: &str) -> Option<bool> {
+            match character {
+                "Joe" => Some(true),
+                "Cat" => Some(false),
+                _ => None,
+            }
+        }
+
+        fn day(&self) -> isize {
+            3
+        }
+
+        fn missions(&self, mission: &str) -> Option<isize> {
+            (mission == "Oil").then_some(2)
+        }
+
+        fn supply(&self, supply: &str) -> Option<isize> {
+            (supply == "Food").then_some(14)
+        }
+    }
+
+    fn eval(source: &str) -> bool {
+        Condition::parse(source).unwrap().eval(&Crew)
+    }
+
+    fn flag(name: &str) -> Box<Condition> {
+        Box::new(Condition::Truthy(Value::Flag(name.to_string())))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Condition::parse("a or b and c"),
+            Ok(Condition::Or(
+                flag("a"),
+                Box::new(Condition::And(flag("b"), flag("c")))
+            ))
+        );
+        assert_eq!(
+            Condition::parse("a && b || c"),
+            Ok(Condition::Or(
+                Box::new(Condition::And(flag("a"), flag("b"))),
+                flag("c")
+            ))
+        );
+        assert!(eval("CatDead or 0 and 0"));
+        assert!(!eval("(CatDead or 0) and 0"));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_atom() {
+        assert_eq!(
+            Condition::parse("not a and b"),
+            Ok(Condition::And(
+                Box::new(Condition::Not(flag("a"))),
+                flag("b")
+            ))
+        );
+        assert_eq!(
+            Condition::parse("!(a or b)"),
+            Ok(Condition::Not(Box::new(Condition::Or(
+                flag("a"),
+                flag("b")
+            ))))
+        );
+        assert!(eval("not not CatDead"));
+        assert!(!eval("!CatDead"));
+        assert!(eval("not CatDead == 0"));
+    }
+
+    #[test]
+    fn negative_numbers() {
+        assert_eq!(
+            Condition::parse("Debt == -3"),
+            Ok(Condition::Compare(
+                Value::Flag("Debt".to_string()),
+                Comparison::Equal,
+                Value::Number(-3)
+            ))
+        );
+        assert!(eval("Debt < -2"));
+        assert!(eval("-1"));
+        assert!(!eval("Debt >= 0"));
+    }
+
+    #[test]
+    fn values_come_from_the_context() {
+        assert!(eval("day == 3"));
+        assert!(eval("favor(Joe) > 15 and favor(Bob) == 0"));
+        assert!(eval("alive(Joe) and not alive(Cat) and alive(Bob) == 0"));
+        assert!(eval("mission(Oil) == 2 and mission(Ice) == 0"));
+        assert!(eval("supply(Food) <= 14 and supply(Oxygen) == 0"));
+        assert!(eval("CatDead == 1 and flag(CatDead) != 0 and Unset == 0"));
+        // Only `flag(..)` reaches flags named like a value
+        assert!(eval("flag(day) == 7 and day == 3"));
+    }
+
+    #[test]
+    fn bad_conditions_are_errors() {
+        for source in [
+            "",
+            "CatDead = 1",
+            "CatDead @ 1",
+            "-",
+            "(CatDead and Joe",
+            "CatDead and Joe)",
+            "CatDead and",
+            "favor(Joe",
+            "favor Joe",
+            "favor(1)",
+            "CatDead Joe",
+            "1 == == 1",
+        ] {
+            assert!(Condition::parse(source).is_err(), "`{source}` parsed");
+        }
+        assert_eq!(
+            Condition::parse("CatDead @ 1"),
+            Err("unexpected `@` in condition `CatDead @ 1`".to_string())
+        );
+    }
+
+    #[test]
+    fn display_parses_back_to_the_same_condition() {
+        for source in [
+            "CatDead",
+            "CatDead == 0 and favor(Joe) > 15",
+            "a or b and c",
+            "(a or b) and c",
+            "a and (b or c) and not d",
+            "a and (b and c)",
+            "a or (b or c) or (d and e)",
+            "(a and b) and (c or d)",
+            "not (a and b) or !c",
+            "not not a",
+            "day >= 2 || mission(Oil) != -1",
+            "alive(Cat) < supply(Food) and flag(favor) <= 3",
+        ] {
+            let condition = Condition::parse(source).unwrap();
+            let shown = condition.to_string();
+            assert_eq!(
+                Condition::parse(&shown),
+                Ok(condition),
+                "`{source}` showed as `{shown}`"
+            );
+        }
+        assert_eq!(
+            Condition::parse("(a or b) and !c").unwrap().to_string(),
+            "(a or b) and not c"
+        );
+        assert_eq!(
+            Condition::parse("a and (b and c)").unwrap().to_string(),
+            "a and (b and c)"
+        );
+        assert_eq!(
+            Condition::parse("(a and b) and c").unwrap().to_string(),
+            "a and b and c"
+        );
+    }
+}
@@ -4,56 +4,32 @@
 //}
 
 use super::{despawn_screen, GameState};
-use crate::load;
+use crate::load::{self, SceneLibrary, SceneLoader};
+use crate::roster::{self, CharacterId, Roster, RosterLoader};
+use crate::story::{DatingContext, Phase, Step};
+use crate::typewriter::{TextSettings, Typewriter};
 use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
     math::ops,
     prelude::*,
+    sprite::Anchor,
     text::{FontSmoothing, LineBreak, TextBounds},
     window::PrimaryWindow,
 };
-use serde::Deserialize;
-
-#[derive(Deserialize, Copy, Clone, Debug)]
-enum MissionType {
-    Water,
-    Explore,
-    Oil,
-    Iron,
-}
-
-#[derive(Deserialize, Debug)]
-enum CharactersType {
-    Joe,
-    Jule,
-    Carle,
-    Fredrick,
-    Diedrick,
-    Cat,
-    Liv,
-    Main,
-}
-
-struct CharactersStatus {
-    character: CharactersType,
-    current_dialogue: DialogueOption,
-    favor: usize,
-    alive: bool,
-}
-
-#[derive(Resource)]
-struct DatingContext {
-    all_characters: Vec<CharactersStatus>,
-    day: usize,
-    cursor: isize,
-    selected_scene: DatingScene,
-    flags: Vec<(String, isize)>,
-    gathered_mission: Vec<MissionType>,
-}
-
-struct DialogueOption {
-    scene_flag: usize,
-    mission: Option<MissionType>,
-}
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+// Portraits of people in a conversation who aren't talking
+const DIMMED: Color = Color::srgb(0.35, 0.35, 0.35);
+// Lines nobody says
+const NARRATION: Color = Color::srgb(0.75, 0.8, 0.9);
+// Seconds each read line shows while skipping
+const SKIP_DELAY: f32 = 0.05;
+// Choices the player took in the history
+const CHOICE: Color = Color::srgb(0.45, 0.85, 0.45);
+// Pixels a second the history scrolls while a key is held, and per wheel notch
+const BACKLOG_SCROLL_SPEED: f32 = 600.0;
+const BACKLOG_LINE_HEIGHT: f32 = 30.0;
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum DatingState {
@@ -64,15 +40,6 @@ enum DatingState {
     Choosing,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct DatingScene {
-    id: String,
-    text: Vec<(Option<CharactersType>, String)>,
-    outcome: Option<Vec<(String, isize)>>,
-    choice: Option<((String, String), (String, String))>,
-    mission: Option<MissionType>,
-}
-
 #[derive(Component)]
 struct FollowsMouse;
 
@@ -101,115 +68,86 @@ struct DatingObj;
 struct TalkObj;
 
 #[derive(Component)]
-struct TextBox(usize);
+struct TextBox;
 
+// Shows who is talking above the text box
 #[derive(Component)]
-struct DatingOption;
+struct NamePlate;
 
-pub fn dating_sim_plugin(app: &mut App) {
-    let _ = load::load_scenes();
+#[derive(Component)]
+struct NameText;
 
-    let janitor_joe = CharactersStatus {
-        character: CharactersType::Joe,
-        current_dialogue: DialogueOption {
-            scene_flag: 2,
-            mission: Some(MissionType::Water),
-        },
-        favor: 20,
-        alive: true,
-    };
+// Which of auto and skip are on
+#[derive(Component)]
+struct ModeText;
 
-    let cat = CharactersStatus {
-        character: CharactersType::Cat,
-        current_dialogue: DialogueOption {
-            scene_flag: 8,
-            mission: None,
-        },
-        favor: 20,
-        alive: true,
-    };
+// The history of what was said, open over the conversation
+#[derive(Component)]
+struct Backlog;
 
-    let granny = CharactersStatus {
-        character: CharactersType::Jule,
-        current_dialogue: DialogueOption {
-            scene_flag: 3,
-            mission: Some(MissionType::Oil),
-        },
-        favor: 20,
-        alive: true,
-    };
+#[derive(Component)]
+struct BacklogList;
 
-    let twin1 = CharactersStatus {
-        character: CharactersType::Fredrick,
-        current_dialogue: DialogueOption {
-            scene_flag: 4,
-            mission: None,
-        },
-        favor: 20,
-        alive: true,
-    };
+// Lines shown this session as scene id and line, skip mode only rushes through these
+#[derive(Resource, Default)]
+struct ReadLines(HashSet<(String, usize)>);
 
-    let twin2 = CharactersStatus {
-        character: CharactersType::Diedrick,
-        current_dialogue: DialogueOption {
-            scene_flag: 4,
-            mission: None,
-        },
-        favor: 20,
-        alive: true,
-    };
+// Someone in the current scene, lit up while they speak
+#[derive(Component)]
+struct TalkPortrait(CharacterId);
 
-    let carly = CharactersStatus {
-        character: CharactersType::Carle,
-        current_dialogue: DialogueOption {
-            scene_flag: 4,
-            mission: None,
-        },
-        favor: 20,
-        alive: true,
-    };
+#[derive(Component)]
+struct ChoiceObj;
 
-    let liv = CharactersStatus {
-        character: CharactersType::Liv,
-        current_dialogue: DialogueOption {
-            scene_flag: 4,
-            mission: None,
-        },
-        favor: 20,
-        alive: true,
-    };
+#[derive(Component)]
+struct DatingOption(usize);
 
-    let characters = vec![janitor_joe, granny, cat, twin1, twin2, carly, liv];
-
-    app.insert_resource(DatingContext {
-        all_characters: characters,
-        day: 1,
-        cursor: 2,
-        selected_scene: DatingScene {
-            id: "1".to_string(),
-            text: vec![
-                (None, "This is a placeholder".to_string()),
-                (None, "This is a second placeholder".to_string()),
-            ],
-            outcome: None,
-            choice: None,
-            mission: None,
-        },
-        flags: vec![],
-        gathered_mission: vec![],
-    });
+#[derive(Component)]
+struct PhaseText;
+
+// Choice whose target scene's requirements are not met
+#[derive(Component)]
+struct Locked;
 
-    app.init_state::<DatingState>();
+pub fn dating_sim_plugin(app: &mut App) {
+    app.init_asset::<SceneLibrary>()
+        .init_asset_loader::<SceneLoader>()
+        .add_systems(Startup, load::load_scenes)
+        .add_systems(Update, update_scenes);
+
+    app.init_asset::<Roster>()
+        .init_asset_loader::<RosterLoader>()
+        .add_systems(Startup, roster::load_roster)
+        .add_systems(Update, update_roster);
+
+    app.insert_resource(DatingContext::new(
+        HashMap::new(),
+        Arc::new(Roster::default()),
+    ));
+
+    app.init_state::<DatingState>()
+        .init_resource::<Typewriter>()
+        .init_resource::<TextSettings>()
+        .init_resource::<ReadLines>();
 
     //genereric
-    app.add_systems(OnEnter(GameState::DatingSim), on_dating_sim)
-        .add_systems(OnExit(GameState::DatingSim), despawn_screen::<DatingObj>);
+    app.add_systems(
+        OnEnter(GameState::DatingSim),
+        (on_dating_sim, spawn_phase_text),
+    )
+    .add_systems(Update, show_phase.run_if(in_state(GameState::DatingSim)))
+    .add_systems(
+        OnExit(GameState::DatingSim),
+        (despawn_screen::<DatingObj>, leave_dating_sim),
+    );
 
     //Chilling
     app.add_systems(OnEnter(DatingState::Chilling), on_chill)
         .add_systems(
             Update,
-            cursor_action.run_if(in_state(DatingState::Chilling)),
+            (end_run, play_scheduled, end_phase, cursor_action)
+                .chain()
+                .run_if(in_state(DatingState::Chilling)),
         );
 
     //Dialogue
@@ -220,24 +158,149 @@ pub fn dating_sim_plugin(app: &mut App) {
     //     )
     //     .add_systems(OnExit(DatingState::Talking), despawn_screen::<TalkObj>);
 
-    //Choices
     app.add_systems(OnEnter(DatingState::Talking), start_talking)
         .add_systems(
             Update,
-            talking_action.run_if(in_state(DatingState::Talking)),
+            (
+                (
+                    // Paused while reading the history
+                    (talking_action, show_text)
+                        .chain()
+                        .run_if(not(any_with_component::<Backlog>)),
+                    // After, so Escape closing the history doesn't end the conversation
+                    toggle_backlog,
+                )
+                    .chain(),
+                scroll_backlog,
+                show_speakers,
+                show_name_plate,
+            )
+                .run_if(in_state(DatingState::Talking)),
         )
         .add_systems(OnExit(DatingState::Talking), despawn_screen::<TalkObj>);
 
+    //Choices
+    app.add_systems(OnEnter(DatingState::Choosing), start_choosing)
+        .add_systems(
+            Update,
+            choosing_action.run_if(in_state(DatingState::Choosing)),
+        )
+        .add_systems(OnExit(DatingState::Choosing), despawn_screen::<ChoiceObj>);
+
     app.add_systems(
         OnExit(DatingState::Chilling),
         (despawn_screen::<Portrait>, despawn_screen::<MissionNot>),
     );
 }
 
+// Rebuilds the scene lookup whenever a scene file is loaded, edited or removed
+fn update_scenes(
+    mut events: EventReader<AssetEvent<SceneLibrary>>,
+    libraries: Res<Assets<SceneLibrary>>,
+    mut context: ResMut<DatingContext>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let mut scenes = HashMap::new();
+    for (_, library) in libraries.iter() {
+        for scene in &library.scenes {
+            if scenes.insert(scene.id.clone(), scene.clone()).is_some() {
+                warn!("Scene {} is defined more than once", scene.id);
+            }
+        }
+    }
+    context.scenes = Arc::new(scenes);
+}
+
+// Takes in the roster once it is loaded and again whenever it is edited
+fn update_roster(
+    mut events: EventReader<AssetEvent<Roster>>,
+    rosters: Res<Assets<Roster>>,
+    mut context: ResMut<DatingContext>,
+) {
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = event {
+            if let Some(roster) = rosters.get(*id) {
+                context.set_roster(Arc::new(roster.clone()));
+            }
+        }
+    }
+}
+
 fn on_dating_sim(mut tmp: ResMut<NextState<DatingState>>) {
     tmp.set(DatingState::Chilling);
 }
 
+fn spawn_phase_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(16.0),
+            left: Val::Px(16.0),
+            ..default()
+        },
+        PhaseText,
+        DatingObj,
+    ));
+}
+
+fn show_phase(context: Res<DatingContext>, mut query: Query<&mut Text, With<PhaseText>>) {
+    let hint = match context.phase {
+        Phase::Morning => "N: head outside",
+        Phase::Expedition => "N: skip the expedition",
+        Phase::Evening if context.day >= context.days => "N: see how it ends",
+        Phase::Evening => "N: go to bed",
+        Phase::Over => "The run is over",
+    };
+    let ship = &context.ship;
+    let label = format!(
+        "Day {} {:?}\nOxygen {} days, food {} days, power {} days\n{hint}",
+        context.day, context.phase, ship.oxygen, ship.food, ship.power
+    );
+    for mut text in &mut query {
+        if text.0 != label {
+            text.0.clone_from(&label);
+        }
+    }
+}
+
+// The scene scheduled for this part of the day plays before anyone can be picked
+fn play_scheduled(mut context: ResMut<DatingContext>, mut tmp: ResMut<NextState<DatingState>>) {
+    if let Some(id) = context.scheduled_scene() {
+        if context.play(&id) {
+            tmp.set(DatingState::Talking);
+        }
+    }
+}
+
+fn end_phase(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut context: ResMut<DatingContext>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyN) || !context.next_phase() {
+        return;
+    }
+    if context.phase == Phase::Expedition {
+        game_state.set(GameState::Explore);
+    }
+}
+
+// Once the ending scene has played, or if there is none
+fn end_run(context: Res<DatingContext>, mut game_state: ResMut<NextState<GameState>>) {
+    if context.phase == Phase::Over && context.scheduled_scene().is_none() {
+        game_state.set(GameState::Ending);
+    }
+}
+
+// The dating states only mean something while on the ship
+fn leave_dating_sim(mut tmp: ResMut<NextState<DatingState>>) {
+    tmp.set(DatingState::Noting);
+}
+
 fn on_chill(
     mut commands: Commands,
     context: ResMut<DatingContext>,
@@ -285,50 +348,23 @@ fn on_chill(
         DatingObj,
     ));
 
-    for (idx, i) in context.all_characters.iter().enumerate() {
+    // Same order the cursor goes through them in cursor_action
+    for (idx, i) in context.alive_crew().enumerate() {
         let size = width / 9.0;
-        let portrait = match i.character {
-            CharactersType::Joe => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Janitor Joe-Recovered.png"),
-                ..Default::default()
-            },
-            CharactersType::Jule => Sprite {
+        let portrait = match context
+            .roster
+            .get(&i.character)
+            .and_then(|info| info.portrait(None))
+        {
+            Some(path) => Sprite {
                 custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_General_Jule.png"),
+                image: asset_server.load(path),
                 ..Default::default()
             },
-            CharactersType::Fredrick => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_Twin_Dedrick.png"),
-                ..Default::default()
-            },
-
-            CharactersType::Diedrick => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_Twin_Fredrick.png"),
-                ..Default::default()
-            },
-
-            CharactersType::Carle => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_Carly.png"),
-                ..Default::default()
-            },
-            CharactersType::Liv => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_Liv.png"),
-                ..Default::default()
-            },
-            CharactersType::Cat => Sprite {
-                custom_size: Some(Vec2::new(size, size)),
-                image: asset_server.load("Portraits/Character_cat.png"),
-                ..Default::default()
-            },
-            _ => Sprite::from_color(Color::srgb(0.25, 0.25, 0.75), Vec2::new(size, size)),
+            None => Sprite::from_color(Color::srgb(0.25, 0.25, 0.75), Vec2::new(size, size)),
         };
 
-        let box_position = dbg!(Vec2::new((idx as f32 * size * 1.2) - width / 2.5, 250.0));
+        let box_position = Vec2::new((idx as f32 * size * 1.2) - width / 2.5, 250.0);
         if let Some(mission_var) = i.current_dialogue.mission {
             let box_size = Vec2::new(size / 1.5, size / 1.5);
             let box_position = box_position + Vec2::new(0.0, -150.0);
@@ -362,6 +398,7 @@ fn start_talking(
     mut query: Query<&mut Transform, With<Cursor>>,
     asset_server: Res<AssetServer>,
     windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut typewriter: ResMut<Typewriter>,
 ) {
     let window = windows.single();
     let width = window.resolution.width();
@@ -374,6 +411,12 @@ fn start_talking(
         ..default()
     };
 
+    let small_text_font = TextFont {
+        font: font.clone(),
+        font_size: 20.0,
+        ..default()
+    };
+
     let slightly_smaller_text_font = TextFont {
         font,
         font_size: 35.0,
@@ -383,7 +426,10 @@ fn start_talking(
     let talk_size = Vec2::new(width / 1.6, width / 10.0);
     let talk_position = Vec2::new(0.0, -150.0);
 
-    let dialogue = context.selected_scene.text[0].1.clone();
+    let dialogue = context
+        .current_line()
+        .map_or(String::new(), |line| context.line_text(line));
+    typewriter.start(&dialogue);
     commands
         .spawn((
             Sprite::from_color(Color::srgb(0.20, 0.3, 0.70), talk_size),
@@ -392,8 +438,8 @@ fn start_talking(
         ))
         .with_children(|builder| {
             builder.spawn((
-                Text2d::new(dialogue),
-                TextBox(0),
+                Text2d::default(),
+                TextBox,
                 slightly_smaller_text_font.clone(),
                 TextLayout::new(JustifyText::Left, LineBreak::AnyCharacter),
                 // Wrap text in the rectangle
@@ -401,45 +447,483 @@ fn start_talking(
                 // ensure the text is drawn on top of the box
                 Transform::from_translation(Vec3::Z),
             ));
+
+            // Sits on the top left corner of the box
+            let plate_size = Vec2::new(talk_size.x / 4.0, talk_size.y / 3.0);
+            let plate_position = Vec2::new(
+                (plate_size.x - talk_size.x) / 2.0,
+                (plate_size.y + talk_size.y) / 2.0,
+            );
+            builder
+                .spawn((
+                    Sprite::from_color(Color::srgb(0.1, 0.15, 0.4), plate_size),
+                    Transform::from_translation(plate_position.extend(1.0)),
+                    Visibility::Hidden,
+                    NamePlate,
+                ))
+                .with_children(|plate| {
+                    plate.spawn((
+                        Text2d::default(),
+                        NameText,
+                        slightly_smaller_text_font.clone(),
+                        TextBounds::from(plate_size),
+                        Transform::from_translation(Vec3::Z),
+                    ));
+                });
+
+            // Under the bottom right corner of the box
+            let mode_position = Vec2::new(talk_size.x / 2.0, -talk_size.y / 2.0 - 5.0);
+            builder.spawn((
+                Text2d::default(),
+                ModeText,
+                small_text_font,
+                Anchor::TopRight,
+                Transform::from_translation(mode_position.extend(1.0)),
+            ));
         });
 }
 
+// The speaker's name in their color, narration has no plate and its own color
+fn show_name_plate(
+    context: Res<DatingContext>,
+    mut plates: Query<&mut Visibility, With<NamePlate>>,
+    mut names: Query<(&mut Text2d, &mut TextColor), With<NameText>>,
+    mut lines: Query<&mut TextColor, (With<TextBox>, Without<NameText>)>,
+) {
+    let speaker = context
+        .current_line()
+        .and_then(|line| context.speaker(line));
+    let visibility = match speaker {
+        Some(_) => Visibility::Inherited,
+        None => Visibility::Hidden,
+    };
+    for mut plate in &mut plates {
+        plate.set_if_neq(visibility);
+    }
+
+    let color = speaker
+        .as_ref()
+        .and_then(|id| context.roster.get(id))
+        .map_or(Color::WHITE, |info| info.color());
+    for (mut text, mut name_color) in &mut names {
+        if let Some(id) = &speaker {
+            let name = context.display_name(id);
+            if text.0 != name {
+                text.0 = name.to_string();
+            }
+        }
+        if name_color.0 != color {
+            name_color.0 = color;
+        }
+    }
+
+    let line_color = if speaker.is_some() {
+        Color::WHITE
+    } else {
+        NARRATION
+    };
+    for mut text_color in &mut lines {
+        if text_color.0 != line_color {
+            text_color.0 = line_color;
+        }
+    }
+}
+
 fn talking_action(
     time: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&mut TextBox, &mut Text2d), With<TextBox>>,
     mut context: ResMut<DatingContext>,
+    mut typewriter: ResMut<Typewriter>,
+    mut settings: ResMut<TextSettings>,
+    mut read_lines: ResMut<ReadLines>,
     mut tmp: ResMut<NextState<DatingState>>,
 ) {
     let confirm = keyboard_input.just_pressed(KeyCode::Enter)
         || keyboard_input.just_pressed(KeyCode::Space)
         || keyboard_input.just_pressed(KeyCode::KeyZ);
-    let escape = keyboard_input.just_pressed(KeyCode::Escape);
+    let escape = keyboard_input.just_pressed(KeyCode::Escape) && context.can_leave;
+    if keyboard_input.just_pressed(KeyCode::KeyA) {
+        settings.auto = !settings.auto;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        settings.skip = !settings.skip;
+    }
+
+    let current = (context.selected_scene.id.clone(), context.line);
+    if settings.skip && !read_lines.0.contains(&current) {
+        settings.skip = false;
+    }
+    typewriter.tick(time.delta_secs(), settings.chars_per_second);
 
-    if escape {
+    // The first press shows the rest of the line, the next one moves on
+    let next_line = if escape {
         tmp.set(DatingState::Chilling);
+        false
+    } else if confirm && !typewriter.is_done() {
+        typewriter.complete();
+        false
     } else if confirm {
-        for (mut textbox, mut text) in &mut query {
-            (*textbox).0 += 1;
-            if (*textbox).0 < context.selected_scene.text.len() {
-                let dialogue = dbg!(context.selected_scene.text[(*textbox).0 as usize].1.clone());
-                *text = Text2d::new(dialogue);
-            } else {
-                //We have finished reading
-                if let Some(mission) = context.selected_scene.mission {
-                    context.gathered_mission.push(mission);
-                }
-                if context.selected_scene.outcome.is_some() {
-                    println!("Added flag, but not implemented")
-                }
-                if context.selected_scene.choice.is_some() {
-                    todo!()
-                    //context.selected_scene = Some(context.selected_scene.choice)[0][1];
-                } else {
-                    tmp.set(DatingState::Chilling);
-                }
+        true
+    } else if settings.skip {
+        typewriter.complete();
+        typewriter.idle >= SKIP_DELAY
+    } else {
+        settings.auto && typewriter.is_done() && typewriter.idle >= typewriter.auto_delay()
+    };
+    if !next_line {
+        return;
+    }
+
+    read_lines.0.insert(current);
+    match context.advance() {
+        // Also covers following a `next` link, OnEnter won't run again for that
+        Step::Line => {
+            let dialogue = context
+                .current_line()
+                .map_or(String::new(), |line| context.line_text(line));
+            typewriter.start(&dialogue);
+        }
+        Step::Choosing => tmp.set(DatingState::Choosing),
+        Step::Finished => tmp.set(DatingState::Chilling),
+    }
+}
+
+fn show_text(
+    typewriter: Res<Typewriter>,
+    settings: Res<TextSettings>,
+    mut lines: Query<&mut Text2d, With<TextBox>>,
+    mut modes: Query<&mut Text2d, (With<ModeText>, Without<TextBox>)>,
+) {
+    for mut text in &mut lines {
+        if text.0 != typewriter.visible() {
+            text.0 = typewriter.visible().to_string();
+        }
+    }
+
+    let mode = match (settings.auto, settings.skip) {
+        (_, true) => "Skipping (F)",
+        (true, false) => "Auto (A)",
+        (false, false) => "A: auto  F: skip read lines  H: history",
+    };
+    for mut text in &mut modes {
+        if text.0 != mode {
+            text.0 = mode.to_string();
+        }
+    }
+}
+
+// The dialogue history over the conversation, newest lines at the bottom
+fn toggle_backlog(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    context: Res<DatingContext>,
+    open: Query<Entity, With<Backlog>>,
+) {
+    if let Ok(backlog) = open.get_single() {
+        if keyboard_input.just_pressed(KeyCode::KeyH)
+            || keyboard_input.just_pressed(KeyCode::Escape)
+        {
+            commands.entity(backlog).despawn_recursive();
+        }
+        return;
+    }
+    if !keyboard_input.just_pressed(KeyCode::KeyH) {
+        return;
+    }
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.9)),
+            Backlog,
+            TalkObj,
+        ))
+        .with_children(|screen| {
+            screen
+                .spawn((
+                    Node {
+                        width: Val::Percent(70.0),
+                        height: Val::Percent(80.0),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.0),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    // Starts at the bottom, layout clamps it to the end of the list
+                    ScrollPosition {
+                        offset_y: f32::MAX,
+                        ..default()
+                    },
+                    BacklogList,
+                ))
+                .with_children(|list| {
+                    if context.history.is_empty() {
+                        list.spawn(Text::new("Nothing has been said yet"));
+                    }
+                    let mut day = None;
+                    for logged in &context.history {
+                        if day != Some(logged.day) {
+                            day = Some(logged.day);
+                            list.spawn((
+                                Text::new(format!("Day {}", logged.day)),
+                                TextFont {
+                                    font_size: 30.0,
+                                    ..default()
+                                },
+                            ));
+                        }
+                        let (line, color) = match &logged.speaker {
+                            _ if logged.choice => (format!("> {}", logged.text), CHOICE),
+                            Some(id) => (
+                                format!("{}: {}", context.display_name(id), logged.text),
+                                context
+                                    .roster
+                                    .get(id)
+                                    .map_or(Color::WHITE, |info| info.color()),
+                            ),
+                            None => (logged.text.clone(), NARRATION),
+                        };
+                        list.spawn((Text::new(line), TextColor(color)));
+                    }
+                });
+            screen.spawn(Text::new("Up/Down: scroll  H: close"));
+        });
+}
+
+fn scroll_backlog(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut wheel: EventReader<MouseWheel>,
+    mut lists: Query<&mut ScrollPosition, With<BacklogList>>,
+) {
+    let mut scroll = 0.0;
+    if keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp) {
+        scroll -= BACKLOG_SCROLL_SPEED * time.delta_secs();
+    }
+    if keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown) {
+        scroll += BACKLOG_SCROLL_SPEED * time.delta_secs();
+    }
+    for event in wheel.read() {
+        scroll -= match event.unit {
+            MouseScrollUnit::Line => event.y * BACKLOG_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+    }
+    for mut position in &mut lists {
+        position.offset_y = (position.offset_y + scroll).max(0.0);
+    }
+}
+
+// One portrait per speaker of the scene above the text box, the one talking shows
+// the expression of their line and everyone else is dimmed
+fn show_speakers(
+    mut commands: Commands,
+    context: Res<DatingContext>,
+    mut portraits: Query<(Entity, &TalkPortrait, &mut Sprite)>,
+    asset_server: Res<AssetServer>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    // Scene the portraits were laid out for, a `next` link keeps us in Talking
+    mut shown_scene: Local<Option<String>>,
+) {
+    if portraits.is_empty() || shown_scene.as_deref() != Some(&context.selected_scene.id) {
+        for (entity, _, _) in &portraits {
+            commands.entity(entity).despawn_recursive();
+        }
+        *shown_scene = Some(context.selected_scene.id.clone());
+
+        let mut speakers: Vec<CharacterId> = Vec::new();
+        for line in &context.selected_scene.text {
+            let Some(speaker) = context.speaker(line) else {
+                continue;
+            };
+            let has_portrait = context
+                .roster
+                .get(&speaker)
+                .is_some_and(|info| info.portrait(None).is_some());
+            if has_portrait && !speakers.contains(&speaker) {
+                speakers.push(speaker);
+            }
+        }
+
+        let width = windows.single().resolution.width();
+        let size = width / 7.0;
+        let row = (speakers.len() as f32 - 1.0) * size * 1.2;
+        for (idx, speaker) in speakers.into_iter().enumerate() {
+            let position = Vec2::new(idx as f32 * size * 1.2 - row / 2.0, 150.0);
+            commands.spawn((
+                Sprite {
+                    custom_size: Some(Vec2::new(size, size)),
+                    color: DIMMED,
+                    ..Default::default()
+                },
+                Transform::from_translation(position.extend(0.0)),
+                TalkPortrait(speaker),
+                TalkObj,
+            ));
+        }
+        // The images are set next frame once the sprites exist
+        return;
+    }
+
+    let line = context.current_line();
+    let talking = line.and_then(|line| context.speaker(line));
+    for (_, TalkPortrait(character), mut sprite) in &mut portraits {
+        let Some(info) = context.roster.get(character) else {
+            continue;
+        };
+        let (expression, color) = if talking.as_ref() == Some(character) {
+            (
+                line.and_then(|line| line.expression.as_deref()),
+                Color::WHITE,
+            )
+        } else {
+            (None, DIMMED)
+        };
+        if let Some(path) = info.portrait(expression) {
+            let image = asset_server.load(path);
+            if sprite.image != image {
+                sprite.image = image;
             }
         }
+        sprite.color = color;
+    }
+}
+
+fn start_choosing(
+    mut commands: Commands,
+    mut context: ResMut<DatingContext>,
+    asset_server: Res<AssetServer>,
+    windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut tmp: ResMut<NextState<DatingState>>,
+) {
+    let window = windows.single();
+    let width = window.resolution.width();
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let slightly_smaller_text_font = TextFont {
+        font,
+        font_size: 35.0,
+        ..default()
+    };
+
+    let options = context.choices();
+
+    let Some(first_playable) = options.iter().position(|(_, playable)| *playable) else {
+        warn!("No choice of {} can be played", context.selected_scene.id);
+        tmp.set(DatingState::Chilling);
+        return;
+    };
+    context.choice_cursor = first_playable;
+
+    let option_size = Vec2::new(width / 1.6, width / 16.0);
+    for (idx, (label, playable)) in options.into_iter().enumerate() {
+        let option_position = Vec2::new(0.0, -100.0 - idx as f32 * option_size.y * 1.3);
+        let mut option = commands.spawn((
+            Sprite::from_color(option_color(idx == first_playable, !playable), option_size),
+            Transform::from_translation(option_position.extend(0.0)),
+            DatingOption(idx),
+            ChoiceObj,
+        ));
+        if !playable {
+            option.insert(Locked);
+        }
+        option.with_children(|builder| {
+            builder.spawn((
+                Text2d::new(label),
+                slightly_smaller_text_font.clone(),
+                TextLayout::new(JustifyText::Center, LineBreak::WordBoundary),
+                TextBounds::from(option_size),
+                Transform::from_translation(Vec3::Z),
+            ));
+        });
+    }
+}
+
+fn option_color(selected: bool, locked: bool) -> Color {
+    match (selected, locked) {
+        (_, true) => Color::srgb(0.4, 0.4, 0.4),
+        (true, false) => Color::srgb(0.25, 0.75, 0.25),
+        (false, false) => Color::srgb(0.20, 0.3, 0.70),
+    }
+}
+
+fn choosing_action(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut options: Query<(&DatingOption, &Transform, &mut Sprite, Has<Locked>)>,
+    mut context: ResMut<DatingContext>,
+    mut tmp: ResMut<NextState<DatingState>>,
+) {
+    let up =
+        keyboard_input.just_pressed(KeyCode::KeyW) || keyboard_input.just_pressed(KeyCode::ArrowUp);
+    let down = keyboard_input.just_pressed(KeyCode::KeyS)
+        || keyboard_input.just_pressed(KeyCode::ArrowDown);
+    let mut confirm = keyboard_input.just_pressed(KeyCode::Enter)
+        || keyboard_input.just_pressed(KeyCode::Space)
+        || keyboard_input.just_pressed(KeyCode::KeyZ);
+
+    let mut unlocked: Vec<usize> = options
+        .iter()
+        .filter(|(_, _, _, locked)| !locked)
+        .map(|(option, _, _, _)| option.0)
+        .collect();
+    unlocked.sort();
+    if let Some(current) = unlocked
+        .iter()
+        .position(|idx| *idx == context.choice_cursor)
+    {
+        if up && current > 0 {
+            context.choice_cursor = unlocked[current - 1];
+        } else if down && current + 1 < unlocked.len() {
+            context.choice_cursor = unlocked[current + 1];
+        }
+    }
+
+    // Hovering an option selects it, clicking it confirms
+    let (camera, camera_transform) = cameras.single();
+    let mouse_position = windows
+        .single()
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+    if let Some(mouse_position) = mouse_position {
+        for (option, transform, sprite, locked) in &options {
+            let half_size = sprite.custom_size.unwrap_or_default() / 2.0;
+            let offset = (mouse_position - transform.translation.truncate()).abs();
+            if !locked && offset.x <= half_size.x && offset.y <= half_size.y {
+                context.choice_cursor = option.0;
+                confirm |= mouse_input.just_pressed(MouseButton::Left);
+            }
+        }
+    }
+
+    for (option, _, mut sprite, locked) in &mut options {
+        sprite.color = option_color(option.0 == context.choice_cursor, locked);
+    }
+
+    if !confirm {
+        return;
+    }
+
+    let choice = context.choice_cursor;
+    if context.choose(choice) {
+        tmp.set(DatingState::Talking);
+    } else {
+        warn!(
+            "Choice {choice} of {} leads nowhere",
+            context.selected_scene.id
+        );
+        tmp.set(DatingState::Chilling);
     }
 }
 
@@ -479,6 +963,7 @@ fn cursor_action(
     time: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut query: Query<&mut Transform, With<Cursor>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
     mut context: ResMut<DatingContext>,
     mut tmp: ResMut<NextState<DatingState>>,
 ) {
@@ -493,13 +978,27 @@ fn cursor_action(
         || keyboard_input.just_pressed(KeyCode::Space)
         || keyboard_input.just_pressed(KeyCode::KeyZ);
 
+    let last = context.alive_crew().count() as isize - 1;
+    context.cursor = (context.cursor - left as isize + right as isize).clamp(0, last.max(0));
+
     if confirm {
-        tmp.set(DatingState::Talking);
+        let selected = context
+            .alive_crew()
+            .nth(context.cursor as usize)
+            .map(|status| status.character.clone());
+        if let Some(character) = selected {
+            if context.talk_to(&character) {
+                tmp.set(DatingState::Talking);
+            } else {
+                info!("{character} has nothing to say");
+            }
+        }
     }
 
-    context.cursor += -(left as isize) + right as isize;
-
+    // Same spacing as the portraits in on_chill
+    let width = windows.single().resolution.width();
+    let size = width / 9.0;
     for mut transform in &mut query {
-        transform.translation.x = (context.cursor * 180) as f32;
+        transform.translation.x = (context.cursor as f32 * size * 1.2) - width / 2.5;
     }
 }
@@ -0,0 +1,115 @@
+//! The end screen shown after the ending scene, with how the captain got along
+//! with everyone and who made it.
+
+use crate::game::ExplorationProgress;
+use crate::save::SavedDay;
+use crate::story::DatingContext;
+use crate::{despawn_screen, GameState};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// How a favor value reads on the end screen, everyone starts at 20
+const RELATIONSHIPS: [(usize, &str); 4] = [
+    (40, "Inseparable"),
+    (30, "Close"),
+    (20, "Friendly"),
+    (0, "Distant"),
+];
+
+#[derive(Component)]
+struct OnEndScreen;
+
+pub fn ending_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Ending), show_end_screen)
+        .add_systems(Update, leave_end_screen.run_if(in_state(GameState::Ending)))
+        .add_systems(
+            OnExit(GameState::Ending),
+            (despawn_screen::<OnEndScreen>, new_game),
+        );
+}
+
+fn relationship(favor: usize) -> &'static str {
+    RELATIONSHIPS
+        .iter()
+        .find(|(min, _)| favor >= *min)
+        .map_or("Distant", |(_, name)| name)
+}
+
+fn show_end_screen(mut commands: Commands, context: Res<DatingContext>) {
+    let title = match context.ending() {
+        Some(ending) => format!("The End ({})", ending.id),
+        None => "The End".to_string(),
+    };
+    let survivors = context
+        .all_characters
+        .iter()
+        .filter(|status| status.alive)
+        .count();
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.05, 0.05, 0.1)),
+            OnEndScreen,
+        ))
+        .with_children(|screen| {
+            screen.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+            ));
+            screen.spawn(Text::new(format!(
+                "{survivors} of {} made it through {} days",
+                context.all_characters.len(),
+                context.day
+            )));
+            for status in &context.all_characters {
+                let (line, color) = if status.alive {
+                    let line = format!(
+                        "{}: {} ({})",
+                        context.display_name(&status.character),
+                        relationship(status.favor),
+                        status.favor
+                    );
+                    (line, Color::WHITE)
+                } else {
+                    let name = context.display_name(&status.character);
+                    let line = format!("{name}: didn't make it");
+                    (line, Color::srgb(0.6, 0.6, 0.6))
+                };
+                screen.spawn((Text::new(line), TextColor(color)));
+            }
+            screen.spawn(Text::new("Enter: back to the menu"));
+        });
+}
+
+fn leave_end_screen(keys: Res<ButtonInput<KeyCode>>, mut state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Escape) {
+        state.set(GameState::Menu);
+    }
+}
+
+// The next run starts from the first day, without autosaving over the finished one
+fn new_game(
+    mut context: ResMut<DatingContext>,
+    mut progress: ResMut<ExplorationProgress>,
+    mut saved_day: ResMut<SavedDay>,
+) {
+    *context = DatingContext {
+        scenes: context.scenes.clone(),
+        captain: context.captain.clone(),
+        ..DatingContext::new(HashMap::new(), context.roster.clone())
+    };
+    *progress = ExplorationProgress::default();
+    saved_day.0 = None;
+}
@@ -0,0 +1,152 @@
+//! The suit only holds so much oxygen per day outside the ship. How much depends on
+//! the day and on the `SuitUpgrade` flag scene outcomes can raise. Running out drags
+//! the player back to the ship and sets the `EvaOvertime` flag.
+
+use crate::story::DatingContext;
+use crate::{despawn_screen, GameState};
+use bevy::prelude::*;
+
+// Seconds of oxygen on each day, the pollen gets worse as the days go on
+const OXYGEN_BY_DAY: [f32; 7] = [120.0, 110.0, 100.0, 90.0, 80.0, 70.0, 60.0];
+// Extra seconds per level of `SuitUpgrade`
+const UPGRADE_SECONDS: f32 = 30.0;
+// Shown once the share of oxygen left drops this low
+const WARNINGS: [(f32, &str); 3] = [
+    (0.5, "Half of the oxygen is gone"),
+    (0.25, "Oxygen low, head back to the ship!"),
+    (0.1, "Oxygen critical!"),
+];
+// Below this the meter turns red
+const LOW_OXYGEN: f32 = 0.25;
+
+pub const UPGRADE_FLAG: &str = "SuitUpgrade";
+pub const OVERTIME_FLAG: &str = "EvaOvertime";
+
+// Oxygen used up on `day`, refilled when a new day starts
+#[derive(Resource, Default)]
+pub struct Oxygen {
+    day: usize,
+    used: f32,
+}
+
+#[derive(Component)]
+struct OnEva;
+
+#[derive(Component)]
+struct OxygenBar;
+
+#[derive(Component)]
+struct OxygenText;
+
+pub fn eva_plugin(app: &mut App) {
+    app.init_resource::<Oxygen>()
+        .add_systems(OnEnter(GameState::Explore), start_eva)
+        .add_systems(Update, breathe.run_if(in_state(GameState::Explore)))
+        .add_systems(OnExit(GameState::Explore), despawn_screen::<OnEva>);
+}
+
+/// Seconds of oxygen the suit holds today.
+pub fn oxygen_capacity(context: &DatingContext) -> f32 {
+    let day = context.day.clamp(1, OXYGEN_BY_DAY.len()) - 1;
+    let upgrades = context.flag(UPGRADE_FLAG).max(0) as f32;
+    OXYGEN_BY_DAY[day] + upgrades * UPGRADE_SECONDS
+}
+
+fn start_eva(
+    mut commands: Commands,
+    context: Res<DatingContext>,
+    mut oxygen: ResMut<Oxygen>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    if oxygen.day != context.day {
+        *oxygen = Oxygen {
+            day: context.day,
+            used: 0.0,
+        };
+    }
+    if oxygen.used >= oxygen_capacity(&context) {
+        info!("The suit is out of oxygen until tomorrow");
+        state.set(GameState::DatingSim);
+        return;
+    }
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                left: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            OnEva,
+        ))
+        .with_children(|hud| {
+            hud.spawn((Text::new(""), OxygenText));
+            hud.spawn((
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(12.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+            ))
+            .with_children(|bar| {
+                bar.spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.3, 0.7, 1.0)),
+                    OxygenBar,
+                ));
+            });
+        });
+}
+
+fn breathe(
+    time: Res<Time>,
+    mut oxygen: ResMut<Oxygen>,
+    mut context: ResMut<DatingContext>,
+    mut state: ResMut<NextState<GameState>>,
+    mut bar: Query<(&mut Node, &mut BackgroundColor), With<OxygenBar>>,
+    mut text: Query<(&mut Text, &mut TextColor), With<OxygenText>>,
+) {
+    let capacity = oxygen_capacity(&context);
+    let before = 1.0 - oxygen.used / capacity;
+    oxygen.used += time.delta_secs();
+    let left = (1.0 - oxygen.used / capacity).max(0.0);
+
+    let mut label = format!("Oxygen {:.0}s", capacity * left);
+    for (share, warning) in WARNINGS {
+        if before > share && left <= share {
+            warn!("{warning}");
+        }
+        if left <= share {
+            label = format!("Oxygen {:.0}s\n{warning}", capacity * left);
+        }
+    }
+
+    let (bar_color, text_color) = if left <= LOW_OXYGEN {
+        let red = Color::srgb(1.0, 0.3, 0.3);
+        (red, red)
+    } else {
+        (Color::srgb(0.3, 0.7, 1.0), Color::WHITE)
+    };
+    for (mut node, mut color) in &mut bar {
+        node.width = Val::Percent(left * 100.0);
+        color.0 = bar_color;
+    }
+    for (mut text, mut color) in &mut text {
+        text.0.clone_from(&label);
+        color.0 = text_color;
+    }
+
+    if left <= 0.0 {
+        info!("Out of oxygen, the crew drags you back inside");
+        context.add_flag(OVERTIME_FLAG, 1);
+        state.set(GameState::DatingSim);
+    }
+}
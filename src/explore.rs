@@ -0,0 +1,258 @@
+//! `cargo run -- --explore [max_steps]` plays every combination of choices, talks and
+//! phases of the day up to `max_steps` inputs deep and reports the dead ends it runs
+//! into.
+
+use crate::load::read_scenes;
+use crate::roster::read_roster;
+use crate::story::{DatingContext, Input, Phase, Step, ENDING_SCENE};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+
+const MAX_STATES: usize = 200_000;
+
+struct State {
+    context: DatingContext,
+    step: Step,
+    parent: Option<usize>,
+    input: Option<Input>,
+    depth: usize,
+}
+
+struct Explorer {
+    states: Vec<State>,
+    seen: HashSet<String>,
+    problems: HashMap<String, usize>,
+}
+
+pub fn run(scene_dir: &Path, roster_path: &Path, max_steps: usize) -> i32 {
+    let loaded = read_scenes(scene_dir).and_then(|scenes| Ok((scenes, read_roster(roster_path)?)));
+    let (scenes, roster) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{err:#}");
+            eprintln!("run with --validate for details");
+            return 1;
+        }
+    };
+
+    let mut explorer = Explorer {
+        states: vec![],
+        seen: HashSet::new(),
+        problems: HashMap::new(),
+    };
+    let hit_limit = explorer.explore(DatingContext::new(scenes, Arc::new(roster)), max_steps);
+
+    println!("Explored {} states", explorer.states.len());
+    if hit_limit {
+        println!("Stopped early after {MAX_STATES} states");
+    }
+
+    let mut problems: Vec<_> = explorer.problems.iter().collect();
+    problems.sort_by_key(|(_, state)| **state);
+    for (problem, state) in &problems {
+        println!("{problem}");
+        println!("    after: {}", explorer.path(**state));
+    }
+
+    if problems.is_empty() {
+        0
+    } else {
+        println!("{} problem(s) found", problems.len());
+        1
+    }
+}
+
+impl Explorer {
+    // Returns true if it gave up before trying everything
+    fn explore(&mut self, start: DatingContext, max_steps: usize) -> bool {
+        self.states.push(State {
+            context: start,
+            step: Step::Finished,
+            parent: None,
+            input: None,
+            depth: 0,
+        });
+        let mut queue = VecDeque::from([0]);
+
+        // A panic is reported as a problem, keep the default hook from spamming stderr
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+
+        while let Some(idx) = queue.pop_front() {
+            if self.states.len() >= MAX_STATES {
+                panic::set_hook(hook);
+                return true;
+            }
+            if self.states[idx].depth >= max_steps {
+                continue;
+            }
+
+            for input in self.inputs(idx) {
+                let state = &self.states[idx];
+                let mut context = state.context.clone();
+                let step = state.step;
+                let applied = panic::catch_unwind(AssertUnwindSafe(|| context.apply(step, &input)));
+
+                let next_step = match applied {
+                    Ok(Some(next_step)) => next_step,
+                    Ok(None) => continue,
+                    Err(panic) => {
+                        let message = panic
+                            .downcast_ref::<String>()
+                            .cloned()
+                            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                            .unwrap_or_default();
+                        self.problem(idx, format!("{input:?} panicked: {message}"));
+                        continue;
+                    }
+                };
+
+                if !self.seen.insert(state_key(&context, next_step)) {
+                    continue;
+                }
+                // Nothing here reads the history, keep it from growing in every state
+                context.history.clear();
+                let depth = state.depth + 1;
+                self.states.push(State {
+                    context,
+                    step: next_step,
+                    parent: Some(idx),
+                    input: Some(input),
+                    depth,
+                });
+                let new = self.states.len() - 1;
+                self.check(new);
+                queue.push_back(new);
+            }
+        }
+
+        panic::set_hook(hook);
+        false
+    }
+
+    fn inputs(&self, idx: usize) -> Vec<Input> {
+        let State { context, step, .. } = &self.states[idx];
+        match step {
+            Step::Line => vec![Input::Skip],
+            Step::Choosing => (0..context.selected_scene.choice.len())
+                .map(Input::Choose)
+                .collect(),
+            Step::Finished => {
+                // Scheduled scenes aren't up to the player, they play before anything else
+                if let Some(id) = context.scheduled_scene() {
+                    return vec![Input::Play(id)];
+                }
+                match context.phase {
+                    Phase::Morning | Phase::Evening => {
+                        let mut inputs: Vec<_> = context
+                            .alive_crew()
+                            .map(|status| Input::Talk(status.character.clone()))
+                            .collect();
+                        inputs.push(Input::NextPhase);
+                        inputs
+                    }
+                    // Nobody to talk to outside
+                    Phase::Expedition => vec![Input::NextPhase],
+                    Phase::Over => vec![],
+                }
+            }
+        }
+    }
+
+    fn check(&mut self, idx: usize) {
+        let State {
+            context,
+            step,
+            input,
+            ..
+        } = &self.states[idx];
+        let scene = &context.selected_scene.id;
+
+        let mut problems = Vec::new();
+        match step {
+            Step::Line if context.current_line().is_none() => {
+                problems.push(format!("scene \"{scene}\" has no lines to show"));
+            }
+            Step::Line if matches!(input, Some(Input::Skip)) => {
+                problems.push(format!("conversation through \"{scene}\" never ends"));
+            }
+            Step::Choosing if context.choices().iter().all(|(_, playable)| !playable) => {
+                problems.push(format!("no choice of scene \"{scene}\" can be picked"));
+            }
+            Step::Finished
+                if context.seen_scenes.iter().any(|id| id == ENDING_SCENE)
+                    && context.ending().is_none() =>
+            {
+                problems.push(format!("no ending fits after day {}", context.day));
+            }
+            Step::Finished
+                if matches!(input, Some(Input::NextPhase)) && context.phase == Phase::Morning =>
+            {
+                let can_talk = context
+                    .all_characters
+                    .iter()
+                    .any(|status| context.clone().talk_to(&status.character));
+                if !can_talk && context.scheduled_scene().is_none() {
+                    problems.push(format!("nobody has anything to say on day {}", context.day));
+                }
+            }
+            _ => {}
+        }
+
+        for (flag, value) in &context.flags {
+            if *value == isize::MAX || *value == isize::MIN {
+                problems.push(format!("flag {flag} overflowed"));
+            }
+        }
+
+        for problem in problems {
+            self.problem(idx, problem);
+        }
+    }
+
+    // Only the first way to reach each problem is kept
+    fn problem(&mut self, idx: usize, problem: String) {
+        self.problems.entry(problem).or_insert(idx);
+    }
+
+    fn path(&self, mut idx: usize) -> String {
+        let mut inputs = Vec::new();
+        while let Some(parent) = self.states[idx].parent {
+            if let Some(input) = &self.states[idx].input {
+                inputs.push(format!("{input:?}"));
+            }
+            idx = parent;
+        }
+        inputs.reverse();
+        if inputs.is_empty() {
+            "the start".to_string()
+        } else {
+            inputs.join(", ")
+        }
+    }
+}
+
+// Two states with the same key play out the same from here on
+fn state_key(context: &DatingContext, step: Step) -> String {
+    let mut flags = context.flags.clone();
+    flags.sort();
+    let mut seen = context.seen_scenes.clone();
+    seen.sort();
+    let mut missions: Vec<_> = context
+        .gathered_mission
+        .iter()
+        .map(|mission| format!("{mission:?}"))
+        .collect();
+    missions.sort();
+    let crew: Vec<_> = context
+        .all_characters
+        .iter()
+        .map(|status| (status.favor, status.alive))
+        .collect();
+    format!(
+        "{step:?}|{}|{:?}|{}|{}|{flags:?}|{seen:?}|{missions:?}|{crew:?}|{:?}",
+        context.day, context.phase, context.selected_scene.id, context.line, context.ship
+    )
+}
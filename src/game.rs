@@ -1,36 +1,158 @@
 use bevy::{
-    asset::{AssetLoader, LoadContext, io::Reader},
+    asset::{io::Reader, AssetLoader, LoadContext, LoadState},
     prelude::*,
 };
 use bevy_rapier2d::prelude::*;
 use image::{self, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use super::{GameState, despawn_screen};
+use super::{despawn_screen, GameState};
+use crate::story::{DatingContext, MissionType, Phase};
+use crate::tiles::{ColorKey, Tile, TileRegistry, TILES_PATH};
 
 pub fn game_plugin(app: &mut App) {
     app.add_plugins((RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),))
         .init_asset_loader::<MapLoader>()
         .init_asset::<MapAsset>()
+        .init_resource::<ExplorationProgress>()
+        .add_event::<TileDug>()
         .add_systems(Startup, load_map)
+        .add_systems(OnEnter(GameState::Explore), spawn_player)
         .add_systems(
-            OnEnter(GameState::Explore),
-            (spawn_player, start_exploration),
+            Update,
+            (
+                (place_player, stream_chunks).chain(),
+                show_map_error,
+                player_movement,
+                track_player,
+                dig,
+                show_dig_marker,
+                rebuild_dug_tiles,
+                return_to_ship,
+            )
+                .run_if(in_state(GameState::Explore)),
         )
-        .add_systems(Update, player_movement.run_if(in_state(GameState::Explore)))
-        .add_systems(OnExit(GameState::Explore), despawn_screen::<OnExploration>);
+        .add_systems(
+            OnExit(GameState::Explore),
+            (
+                despawn_screen::<OnExploration>,
+                unload_goods,
+                end_expedition,
+            ),
+        );
 }
 
 // The float value is the player movement speed in 'pixels/second'.
 #[derive(Component)]
 pub struct Player(f32);
 
+// Direction the player last moved in, along one axis
+#[derive(Component)]
+pub struct Facing(Vec2);
+
+// The tile being dug into and how far along it is, from 0 to 1
+#[derive(Component, Default)]
+struct Digging(Option<(UVec2, f32)>);
+
+#[derive(Event)]
+struct TileDug(UVec2);
+
+#[derive(Component)]
+struct DigMarker;
+
 #[derive(Component)]
 struct OnExploration;
 
+// Parent of the sprites and colliders of one spawned chunk
+#[derive(Component)]
+struct LoadedChunk(UVec2);
+
+// A sprite or collider of a chunk and the tiles it covers
+#[derive(Component)]
+struct ChunkPart {
+    area: URect,
+    collider: bool,
+}
+
+#[derive(Component)]
+struct MapErrorText;
+
+const MAP_PATH: &str = "cave.map.png";
+const PLAYER_SIZE: f32 = 100.0;
+// Side of a chunk in tiles
+const CHUNK_SIZE: usize = 32;
+// Chunks up to this many chunks away from the player's one stay spawned
+const STREAM_RADIUS: i32 = 2;
+
+// Survives leaving the exploration and is saved with the game
+#[derive(Resource, Default)]
+pub struct ExplorationProgress {
+    // None until a new game puts the player on the map's spawn tile
+    pub position: Option<Vec2>,
+    // Tiles that are air now, the map asset itself never changes
+    pub dug: HashSet<UVec2>,
+    pub inventory: Inventory,
+}
+
+/// Goods carried back from an expedition, only so much fits in the pack.
+#[derive(Default, Debug)]
+pub struct Inventory {
+    pub goods: HashMap<MissionType, usize>,
+}
+
+pub const INVENTORY_CAPACITY: usize = 10;
+
+impl Inventory {
+    pub fn total(&self) -> usize {
+        self.goods.values().sum()
+    }
+
+    // False when it's full
+    pub fn add(&mut self, item: MissionType) -> bool {
+        if self.total() >= INVENTORY_CAPACITY {
+            return false;
+        }
+        *self.goods.entry(item).or_default() += 1;
+        true
+    }
+}
+
 #[derive(Asset, TypePath, Debug)]
 struct MapAsset {
-    // 1000x1000
-    tiles: Vec<[Tile; 1000]>,
+    width: usize,
+    height: usize,
+    // Row by row, chunks past the edges are padded with air
+    chunks: Vec<Chunk>,
+    registry: TileRegistry,
+    settings: MapSettings,
+}
+
+/// How a map image is placed in the world, set per map in its `.meta` file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapSettings {
+    /// Side of a tile in pixels
+    pub tile_size: f32,
+    /// Where the map's top left corner is in the world
+    pub origin: [f32; 2],
+    /// Tile a new game starts on, the rock around it is dug out
+    pub spawn: [u32; 2],
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        MapSettings {
+            tile_size: 32.0,
+            origin: [0.0, 0.0],
+            spawn: [500, 20],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    // [y][x]
+    tiles: [[Tile; CHUNK_SIZE]; CHUNK_SIZE],
 }
 
 #[derive(Resource)]
@@ -42,33 +164,212 @@ struct ExplorationMap {
 struct MapLoader;
 
 impl MapAsset {
-    fn from_image(image: &image::DynamicImage) -> MapAsset {
-        assert!(image.width() == 1000 && image.height() == 1000);
+    fn new(width: usize, height: usize, registry: TileRegistry, settings: MapSettings) -> MapAsset {
+        let chunk = Chunk {
+            tiles: [[Tile::Air; CHUNK_SIZE]; CHUNK_SIZE],
+        };
+        let chunks = width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE);
+        MapAsset {
+            width,
+            height,
+            chunks: vec![chunk; chunks],
+            registry,
+            settings,
+        }
+    }
+
+    fn from_image(
+        image: &image::DynamicImage,
+        registry: TileRegistry,
+        settings: MapSettings,
+    ) -> anyhow::Result<MapAsset> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut map = MapAsset::new(width, height, registry, settings);
+        let mut unknown: BTreeMap<ColorKey, Vec<(u32, u32)>> = BTreeMap::new();
+
+        for (x, y, color) in image.pixels() {
+            let [r, g, b, _] = color.0;
+            match map.registry.tile_with_color([r, g, b]) {
+                Some(tile) => map.set_tile(x as usize, y as usize, tile),
+                None => unknown.entry(ColorKey([r, g, b])).or_default().push((x, y)),
+            }
+        }
 
-        let mut tiles = Vec::new();
+        if unknown.is_empty() {
+            return Ok(map);
+        }
+        // A few pixels per color is enough to find the brush stroke in an editor
+        let colors: Vec<_> = unknown
+            .iter()
+            .map(|(color, pixels)| {
+                let shown: Vec<_> = pixels
+                    .iter()
+                    .take(5)
+                    .map(|(x, y)| format!("({x}, {y})"))
+                    .collect();
+                let more = match pixels.len() - shown.len() {
+                    0 => String::new(),
+                    more => format!(" and {more} more"),
+                };
+                format!("{color} at {}{more}", shown.join(", "))
+            })
+            .collect();
+        anyhow::bail!("unknown tile colors:\n{}", colors.join("\n"))
+    }
+
+    // Size in chunks
+    fn chunks_size(&self) -> UVec2 {
+        UVec2::new(
+            self.width.div_ceil(CHUNK_SIZE) as u32,
+            self.height.div_ceil(CHUNK_SIZE) as u32,
+        )
+    }
+
+    fn chunk_index(&self, x: usize, y: usize) -> usize {
+        (y / CHUNK_SIZE) * self.width.div_ceil(CHUNK_SIZE) + x / CHUNK_SIZE
+    }
+
+    fn tile(&self, x: usize, y: usize) -> Tile {
+        self.chunks[self.chunk_index(x, y)].tiles[y % CHUNK_SIZE][x % CHUNK_SIZE]
+    }
+
+    fn set_tile(&mut self, x: usize, y: usize, tile: Tile) {
+        let idx = self.chunk_index(x, y);
+        self.chunks[idx].tiles[y % CHUNK_SIZE][x % CHUNK_SIZE] = tile;
+    }
+
+    // The tile under a point in the world
+    fn world_to_tile(&self, point: Vec2) -> Option<UVec2> {
+        let offset = (point - Vec2::from_array(self.settings.origin)) * Vec2::new(1.0, -1.0);
+        let tile = (offset / self.settings.tile_size).floor();
+        let inside = tile.x >= 0.0
+            && tile.y >= 0.0
+            && (tile.x as usize) < self.width
+            && (tile.y as usize) < self.height;
+        inside.then(|| tile.as_uvec2())
+    }
+
+    fn tile_center(&self, tile: UVec2) -> Vec2 {
+        let center = (tile.as_vec2() + 0.5) * Vec2::new(1.0, -1.0);
+        Vec2::from_array(self.settings.origin) + center * self.settings.tile_size
+    }
+
+    // Tiles covered by a chunk, cut off at the map's edges
+    fn chunk_area(&self, chunk: UVec2) -> URect {
+        let min = chunk * CHUNK_SIZE as u32;
+        let max = (min + CHUNK_SIZE as u32).min(UVec2::new(self.width as u32, self.height as u32));
+        URect::from_corners(min, max)
+    }
+
+    /// Covers `area` with as few rectangles of tiles with the same `key` as it
+    /// reasonably can, tiles with no key are left out. `dug` tiles count as air.
+    fn merge_tiles<K: PartialEq>(
+        &self,
+        area: URect,
+        dug: &HashSet<UVec2>,
+        key: impl Fn(Tile) -> Option<K>,
+    ) -> Vec<TileRect<K>> {
+        let origin = (area.min.x as usize, area.min.y as usize);
+        let (width, height) = (area.width() as usize, area.height() as usize);
+        let tile = |x: usize, y: usize| {
+            let (x, y) = (origin.0 + x, origin.1 + y);
+            match dug.contains(&UVec2::new(x as u32, y as u32)) {
+                true => Tile::Air,
+                false => self.tile(x, y),
+            }
+        };
+        let mut covered = vec![false; width * height];
+        let mut rects = Vec::new();
 
-        for x in 0..1000 {
-            let mut next_row = [Tile::Error; 1000];
-            for y in 0..1000 {
-                let color = image.get_pixel(x, y).0;
-                next_row[x as usize] = tile_from_color(color);
+        for y in 0..height {
+            for x in 0..width {
+                if covered[y * width + x] {
+                    continue;
+                }
+                let Some(rect_key) = key(tile(x, y)) else {
+                    continue;
+                };
+                let fits = |x: usize, y: usize| {
+                    !covered[y * width + x] && key(tile(x, y)).as_ref() == Some(&rect_key)
+                };
+
+                // Grow right as far as the run goes, then down while whole rows match
+                let mut w = 1;
+                while x + w < width && fits(x + w, y) {
+                    w += 1;
+                }
+                let mut h = 1;
+                while y + h < height && (x..x + w).all(|x| fits(x, y + h)) {
+                    h += 1;
+                }
+
+                for row in y..y + h {
+                    covered[row * width + x..row * width + x + w].fill(true);
+                }
+                rects.push(TileRect {
+                    x: origin.0 + x,
+                    y: origin.1 + y,
+                    width: w,
+                    height: h,
+                    key: rect_key,
+                });
             }
-            tiles.push(next_row);
         }
 
-        MapAsset { tiles }
+        rects
+    }
+
+    fn sprite_rects(&self, area: URect, dug: &HashSet<UVec2>) -> Vec<TileRect<Tile>> {
+        self.merge_tiles(area, dug, |tile| (tile != Tile::Air).then_some(tile))
+    }
+
+    // Neighbouring tiles that feel the same under foot share a collider
+    fn collider_rects(&self, area: URect, dug: &HashSet<UVec2>) -> Vec<TileRect<f32>> {
+        self.merge_tiles(area, dug, |tile| {
+            let info = self.registry.get(tile);
+            info.solid.then_some(info.friction)
+        })
+    }
+}
+
+struct TileRect<K> {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    key: K,
+}
+
+impl<K> TileRect<K> {
+    fn area(&self) -> URect {
+        let min = UVec2::new(self.x as u32, self.y as u32);
+        URect::from_corners(min, min + UVec2::new(self.width as u32, self.height as u32))
+    }
+
+    fn size(&self, settings: &MapSettings) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * settings.tile_size
+    }
+
+    // Map rows go down the image, the world's y goes up
+    fn transform(&self, settings: &MapSettings) -> Transform {
+        let center = Vec2::new(
+            self.x as f32 + self.width as f32 / 2.0,
+            -(self.y as f32 + self.height as f32 / 2.0),
+        );
+        let position = Vec2::from_array(settings.origin) + center * settings.tile_size;
+        Transform::from_translation(position.extend(0.0))
     }
 }
 
 impl AssetLoader for MapLoader {
     type Asset = MapAsset;
-    type Settings = ();
+    type Settings = MapSettings;
     type Error = anyhow::Error;
     async fn load(
         &self,
         reader: &mut dyn Reader,
-        _settings: &(),
-        _load_context: &mut LoadContext<'_>,
+        settings: &MapSettings,
+        load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         use std::io::{self, BufRead, Read, Seek};
 
@@ -78,90 +379,257 @@ impl AssetLoader for MapLoader {
         let image = image::ImageReader::new(cursor)
             .with_guessed_format()?
             .decode()?;
-        Ok(MapAsset::from_image(&image))
-    }
 
-    fn extensions(&self) -> &[&str] {
-        &["custom"]
+        // Also reloads the map when the tiles change
+        let tiles = load_context.read_asset_bytes(TILES_PATH).await?;
+        // Only the top message makes it into bevy's error, so keep it all in there
+        let registry = TileRegistry::parse(std::str::from_utf8(&tiles)?)
+            .map_err(|err| anyhow::anyhow!("{TILES_PATH}: {err:#}"))?;
+        MapAsset::from_image(&image, registry, settings.clone())
     }
-}
 
-fn tile_from_color(color: [u8; 4]) -> Tile {
-    match u32::from_be_bytes(color) & 0xFFFFFF {
-        0xFF_FF_FF => Tile::Air,
-        0xDD_DD_DD => Tile::Rock,
-        0x00_00_FF => Tile::Ice,
-        _ => Tile::Error,
+    fn extensions(&self) -> &[&str] {
+        &["map.png"]
     }
 }
 
 fn load_map(mut commands: Commands, asset_server: ResMut<AssetServer>) {
-    let map: Handle<MapAsset> = asset_server.load("map.png");
+    let map: Handle<MapAsset> = asset_server.load(MAP_PATH);
     commands.insert_resource(ExplorationMap { map });
 }
 
-pub fn start_exploration(commands: Commands) {}
-
-#[derive(Copy, Clone, Debug, Default)]
-enum Tile {
-    #[default]
-    Error = 0,
-    Rock,
-    Ice,
-    Oil,
-    Iron,
-    Air,
+// A broken map shows what's wrong with it instead of an empty cave, until it's fixed
+fn show_map_error(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    map: Res<ExplorationMap>,
+    shown: Query<Entity, With<MapErrorText>>,
+) {
+    match server.load_state(&map.map) {
+        LoadState::Failed(err) => {
+            if shown.is_empty() {
+                error!("{err}");
+                commands.spawn((
+                    Text::new(format!("The map failed to load\n\n{err}")),
+                    TextColor(Color::srgb(1.0, 0.4, 0.4)),
+                    Node {
+                        margin: UiRect::all(Val::Auto),
+                        max_width: Val::Percent(80.0),
+                        ..default()
+                    },
+                    MapErrorText,
+                    OnExploration,
+                ));
+            }
+        }
+        _ => {
+            for entity in &shown {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
 }
 
-impl Tile {
-    fn is_solid(&self) -> bool {
-        use Tile as T;
-        match self {
-            T::Error | T::Rock | T::Ice | T::Oil | T::Iron => true,
-            T::Air => false,
+// Spawns the chunks around the player and despawns the ones left behind
+fn stream_chunks(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    map: Res<ExplorationMap>,
+    maps: Res<Assets<MapAsset>>,
+    progress: Res<ExplorationProgress>,
+    player: Query<&Transform, With<Player>>,
+    loaded: Query<(Entity, &LoadedChunk)>,
+) {
+    let Some(map) = maps.get(&map.map) else {
+        return;
+    };
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    // Map rows go down, the world's y goes up
+    let settings = &map.settings;
+    let chunk_pixels = settings.tile_size * CHUNK_SIZE as f32;
+    let offset = player.translation.truncate() - Vec2::from_array(settings.origin);
+    let position = offset * Vec2::new(1.0, -1.0);
+    let center = (position / chunk_pixels).floor().as_ivec2();
+    let near = |chunk: IVec2| (chunk - center).abs().max_element() <= STREAM_RADIUS;
+
+    let mut spawned = Vec::new();
+    for (entity, chunk) in &loaded {
+        if near(chunk.0.as_ivec2()) {
+            spawned.push(chunk.0);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let size = map.chunks_size().as_ivec2();
+    for y in center.y - STREAM_RADIUS..=center.y + STREAM_RADIUS {
+        for x in center.x - STREAM_RADIUS..=center.x + STREAM_RADIUS {
+            if x < 0 || y < 0 || x >= size.x || y >= size.y {
+                continue;
+            }
+            let chunk = UVec2::new(x as u32, y as u32);
+            if !spawned.contains(&chunk) {
+                spawn_chunk(&mut commands, &server, map, &progress.dug, chunk);
+            }
         }
     }
 }
 
+fn spawn_chunk(
+    commands: &mut Commands,
+    server: &AssetServer,
+    map: &MapAsset,
+    dug: &HashSet<UVec2>,
+    chunk: UVec2,
+) {
+    let area = map.chunk_area(chunk);
+    commands
+        .spawn((
+            LoadedChunk(chunk),
+            Transform::default(),
+            Visibility::default(),
+            OnExploration,
+        ))
+        .with_children(|parent| {
+            for rect in &map.sprite_rects(area, dug) {
+                spawn_sprite(parent, server, map, rect);
+            }
+            for rect in &map.collider_rects(area, dug) {
+                spawn_collider(parent, map, rect);
+            }
+        });
+}
+
+fn spawn_sprite(
+    parent: &mut ChildBuilder,
+    server: &AssetServer,
+    map: &MapAsset,
+    rect: &TileRect<Tile>,
+) {
+    let info = map.registry.get(rect.key);
+    let [r, g, b] = info.color.0;
+    let sprite = match &info.sprite {
+        Some(path) => Sprite {
+            image: server.load(path),
+            custom_size: Some(rect.size(&map.settings)),
+            image_mode: SpriteImageMode::Tiled {
+                tile_x: true,
+                tile_y: true,
+                stretch_value: 1.0,
+            },
+            ..Default::default()
+        },
+        None => Sprite::from_color(Color::srgb_u8(r, g, b), rect.size(&map.settings)),
+    };
+    parent.spawn((
+        sprite,
+        rect.transform(&map.settings),
+        ChunkPart {
+            area: rect.area(),
+            collider: false,
+        },
+    ));
+}
+
+fn spawn_collider(parent: &mut ChildBuilder, map: &MapAsset, rect: &TileRect<f32>) {
+    let half = rect.size(&map.settings) / 2.0;
+    parent.spawn((
+        RigidBody::Fixed,
+        Collider::cuboid(half.x, half.y),
+        Friction::coefficient(rect.key),
+        rect.transform(&map.settings),
+        ChunkPart {
+            area: rect.area(),
+            collider: true,
+        },
+    ));
+}
+
 pub fn spawn_player(
     mut commands: Commands,
     server: Res<AssetServer>,
+    progress: Res<ExplorationProgress>,
     mut rapier_config: Query<&mut RapierConfiguration>,
 ) {
     let mut rapier_config = rapier_config.single_mut();
     // Set gravity to 0.0 and spawn camera.
     rapier_config.gravity = Vec2::ZERO;
 
-    let sprite_size = 100.0;
-
     // Spawn entity with `Player` struct as a component for access in movement query.
     commands.spawn((
         Sprite {
-            custom_size: Some(Vec2::new(sprite_size, sprite_size)),
+            custom_size: Some(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
             image: server.load("mascot.png"),
             image_mode: SpriteImageMode::Auto,
             ..Default::default()
         },
+        // In front of the cave
+        Transform::from_translation(progress.position.unwrap_or_default().extend(1.0)),
         RigidBody::Dynamic,
         Velocity::zero(),
-        Collider::ball(sprite_size / 2.0),
+        Collider::ball(PLAYER_SIZE / 2.0),
         Player(100.0),
+        Facing(Vec2::X),
+        Digging::default(),
         OnExploration,
     ));
 
     commands.spawn((
-        RigidBody::Fixed,
-        Collider::cuboid(500.0, 50.0),
-        Transform::from_xyz(0.0, -50.0, 0.0),
+        Sprite::default(),
+        Transform::default(),
+        Visibility::Hidden,
+        DigMarker,
         OnExploration,
     ));
 }
 
+// A new game starts on the map's spawn tile with room dug out around the player
+fn place_player(
+    map: Res<ExplorationMap>,
+    maps: Res<Assets<MapAsset>>,
+    mut progress: ResMut<ExplorationProgress>,
+    mut player: Query<&mut Transform, With<Player>>,
+) {
+    if progress.position.is_some() {
+        return;
+    }
+    let (Some(map), Ok(mut transform)) = (maps.get(&map.map), player.get_single_mut()) else {
+        return;
+    };
+
+    let last = UVec2::new(map.width as u32, map.height as u32).saturating_sub(UVec2::ONE);
+    let spawn = UVec2::from_array(map.settings.spawn).min(last);
+    let room = (PLAYER_SIZE / 2.0 / map.settings.tile_size).ceil() as u32;
+    let min = spawn.saturating_sub(UVec2::splat(room));
+    let max = (spawn + room).min(last);
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            progress.dug.insert(UVec2::new(x, y));
+        }
+    }
+
+    let position = map.tile_center(spawn);
+    transform.translation = position.extend(transform.translation.z);
+    progress.position = Some(position);
+}
+
+fn track_player(
+    player: Query<&Transform, With<Player>>,
+    mut progress: ResMut<ExplorationProgress>,
+) {
+    if let (Ok(transform), Some(position)) = (player.get_single(), &mut progress.position) {
+        *position = transform.translation.truncate();
+    }
+}
+
 pub fn player_movement(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut player_info: Query<(&Player, &mut Velocity)>,
+    mut player_info: Query<(&Player, &mut Velocity, &mut Facing)>,
 ) {
-    for (player, mut rb_vels) in &mut player_info {
+    for (player, mut rb_vels, mut facing) in &mut player_info {
         let up = keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]);
         let down = keyboard_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
         let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
@@ -173,6 +641,10 @@ pub fn player_movement(
         let mut move_delta = Vec2::new(x_axis as f32, y_axis as f32);
         if move_delta != Vec2::ZERO {
             move_delta /= move_delta.length();
+            facing.0 = match x_axis {
+                0 => Vec2::new(0.0, y_axis as f32),
+                _ => Vec2::new(x_axis as f32, 0.0),
+            };
         }
 
         // Update the velocity on the rigid_body_component,
@@ -180,3 +652,143 @@ pub fn player_movement(
         rb_vels.linvel = move_delta * player.0;
     }
 }
+
+// Holding space digs into the solid tile in front of the player, harder tiles take longer
+fn dig(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    map: Res<ExplorationMap>,
+    maps: Res<Assets<MapAsset>>,
+    mut progress: ResMut<ExplorationProgress>,
+    mut player: Query<(&Transform, &Facing, &mut Digging)>,
+    mut dug: EventWriter<TileDug>,
+) {
+    let Some(map) = maps.get(&map.map) else {
+        return;
+    };
+    let Ok((transform, facing, mut digging)) = player.get_single_mut() else {
+        return;
+    };
+
+    let reach = PLAYER_SIZE / 2.0 + map.settings.tile_size / 2.0;
+    let target = map
+        .world_to_tile(transform.translation.truncate() + facing.0 * reach)
+        .filter(|tile| !progress.dug.contains(tile))
+        .map(|tile| {
+            (
+                tile,
+                map.registry.get(map.tile(tile.x as usize, tile.y as usize)),
+            )
+        })
+        .filter(|(_, info)| info.solid && keys.pressed(KeyCode::Space));
+    let Some((target, info)) = target else {
+        digging.0 = None;
+        return;
+    };
+
+    let dug_for = match digging.0 {
+        Some((tile, dug_for)) if tile == target => dug_for,
+        _ => 0.0,
+    } + time.delta_secs();
+    if dug_for < info.hardness {
+        digging.0 = Some((target, dug_for / info.hardness));
+        return;
+    }
+
+    digging.0 = None;
+    progress.dug.insert(target);
+    match info.drop {
+        Some(drop) if progress.inventory.add(drop) => info!("Dug up {drop:?}"),
+        Some(drop) => info!("No room left for {drop:?}"),
+        None => {}
+    }
+    dug.send(TileDug(target));
+}
+
+fn show_dig_marker(
+    map: Res<ExplorationMap>,
+    maps: Res<Assets<MapAsset>>,
+    digging: Query<&Digging>,
+    mut marker: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<DigMarker>>,
+) {
+    let (Some(map), Ok(digging), Ok((mut transform, mut sprite, mut visibility))) = (
+        maps.get(&map.map),
+        digging.get_single(),
+        marker.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    let Some((tile, done)) = digging.0 else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    transform.translation = map.tile_center(tile).extend(2.0);
+    sprite.custom_size = Some(Vec2::splat(map.settings.tile_size));
+    sprite.color = Color::srgba(0.0, 0.0, 0.0, 0.8 * done);
+    *visibility = Visibility::Visible;
+}
+
+// Swaps the sprites and colliders covering a dug tile for ones that leave it out, in
+// the same frame so there is never a gap to fall through
+fn rebuild_dug_tiles(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    map: Res<ExplorationMap>,
+    maps: Res<Assets<MapAsset>>,
+    progress: Res<ExplorationProgress>,
+    mut dug: EventReader<TileDug>,
+    parts: Query<(Entity, &Parent, &ChunkPart)>,
+) {
+    let Some(map) = maps.get(&map.map) else {
+        dug.clear();
+        return;
+    };
+    // Rebuilt parts already leave out every dug tile
+    let mut rebuilt = HashSet::new();
+    for TileDug(tile) in dug.read() {
+        for (entity, chunk, part) in &parts {
+            let inside = tile.cmpge(part.area.min).all() && tile.cmplt(part.area.max).all();
+            if !inside || !rebuilt.insert(entity) {
+                continue;
+            }
+            commands.entity(entity).despawn_recursive();
+            commands.entity(chunk.get()).with_children(|parent| {
+                if part.collider {
+                    for rect in &map.collider_rects(part.area, &progress.dug) {
+                        spawn_collider(parent, map, rect);
+                    }
+                } else {
+                    for rect in &map.sprite_rects(part.area, &progress.dug) {
+                        spawn_sprite(parent, &server, map, rect);
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn return_to_ship(keys: Res<ButtonInput<KeyCode>>, mut state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        state.set(GameState::DatingSim);
+    }
+}
+
+// Whatever the crew asked for gets handed over once back on the ship
+fn unload_goods(mut context: ResMut<DatingContext>, mut progress: ResMut<ExplorationProgress>) {
+    for mission in context.deliver(&mut progress.inventory.goods) {
+        match mission.from {
+            Some(from) => info!("{from}'s {:?} mission is done", mission.kind),
+            None => info!("{:?} mission is done", mission.kind),
+        }
+    }
+    // The rest goes into the ship's stores
+    context.ship.stock(&mut progress.inventory.goods);
+}
+
+// Coming back inside starts the evening, unless the menu sent the player out
+fn end_expedition(mut context: ResMut<DatingContext>) {
+    if context.phase == Phase::Expedition {
+        context.next_phase();
+    }
+}
@@ -0,0 +1,255 @@
+//! `cargo run -- --graph dot` (or `--graph mermaid`) prints how the scenes link together.
+//! Scenes are colored by the day they belong to, dashed edges are `next` links and
+//! hexagons are the missions a scene hands out.
+
+use crate::load::read_scenes;
+use crate::story::DatingScene;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+const DAY_COLORS: [&str; 7] = [
+    "#fde2e4", "#fff1c1", "#d8f3dc", "#cde7f0", "#e2d9f3", "#ffd8be", "#e9ecef",
+];
+const MISSION_COLOR: &str = "#ffd27f";
+
+enum NodeKind {
+    Scene(Option<usize>),
+    Mission,
+    Missing,
+}
+
+struct Node {
+    name: String,
+    kind: NodeKind,
+}
+
+enum EdgeKind {
+    Choice,
+    Next,
+    Mission,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    label: String,
+    kind: EdgeKind,
+}
+
+struct SceneGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+pub fn run(scene_dir: &Path, format: &str) -> i32 {
+    let mut scenes: Vec<_> = match read_scenes(scene_dir) {
+        Ok(scenes) => scenes.into_values().collect(),
+        Err(err) => {
+            eprintln!("{err:#}");
+            eprintln!("run with --validate for details");
+            return 1;
+        }
+    };
+    scenes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let graph = SceneGraph::new(&scenes);
+    match format {
+        "dot" => print!("{}", graph.dot()),
+        "mermaid" => print!("{}", graph.mermaid()),
+        _ => {
+            eprintln!("unknown graph format {format}, use dot or mermaid");
+            return 1;
+        }
+    }
+    0
+}
+
+impl SceneGraph {
+    fn new(scenes: &[DatingScene]) -> SceneGraph {
+        let mut nodes = Vec::new();
+        let mut by_id = HashMap::new();
+        for scene in scenes {
+            by_id.entry(scene.id.clone()).or_insert_with(|| {
+                nodes.push(Node {
+                    name: scene.id.clone(),
+                    kind: NodeKind::Scene(scene.day()),
+                });
+                nodes.len() - 1
+            });
+        }
+
+        let mut edges = Vec::new();
+        let mut missions = HashMap::new();
+        let mut target_node = |nodes: &mut Vec<Node>, id: &str| -> usize {
+            *by_id.entry(id.to_string()).or_insert_with(|| {
+                nodes.push(Node {
+                    name: id.to_string(),
+                    kind: NodeKind::Missing,
+                });
+                nodes.len() - 1
+            })
+        };
+        let requires = |id: &str| -> Option<String> {
+            let scene = scenes.iter().find(|scene| scene.id == id)?;
+            Some(scene.requires.as_ref()?.to_string())
+        };
+
+        for scene in scenes {
+            let from = target_node(&mut nodes, &scene.id);
+            for (label, target) in &scene.choice {
+                let label = match requires(target) {
+                    Some(condition) => format!("{label} [{condition}]"),
+                    None => label.clone(),
+                };
+                let to = target_node(&mut nodes, target);
+                edges.push(Edge {
+                    from,
+                    to,
+                    label,
+                    kind: EdgeKind::Choice,
+                });
+            }
+            for target in &scene.next {
+                let to = target_node(&mut nodes, target);
+                edges.push(Edge {
+                    from,
+                    to,
+                    label: requires(target).unwrap_or_default(),
+                    kind: EdgeKind::Next,
+                });
+            }
+            if let Some(mission) = scene.mission {
+                let name = format!("{mission:?}");
+                let to = *missions.entry(name.clone()).or_insert_with(|| {
+                    nodes.push(Node {
+                        name,
+                        kind: NodeKind::Mission,
+                    });
+                    nodes.len() - 1
+                });
+                edges.push(Edge {
+                    from,
+                    to,
+                    label: String::new(),
+                    kind: EdgeKind::Mission,
+                });
+            }
+        }
+
+        let mut graph = SceneGraph { nodes, edges };
+        graph.spread_days();
+        graph
+    }
+
+    // Scenes without a day in their name belong to the first day found leading to them
+    fn spread_days(&mut self) {
+        loop {
+            let mut changed = false;
+            for edge in &self.edges {
+                let NodeKind::Scene(Some(day)) = self.nodes[edge.from].kind else {
+                    continue;
+                };
+                if let NodeKind::Scene(to_day @ None) = &mut self.nodes[edge.to].kind {
+                    *to_day = Some(day);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("digraph scenes {\n    rankdir=LR;\n");
+        out.push_str("    node [shape=box, style=filled, fillcolor=white];\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let name = dot_escape(&node.name);
+            let style = match node.kind {
+                NodeKind::Scene(Some(day)) => format!(", fillcolor=\"{}\"", day_color(day)),
+                NodeKind::Scene(None) => String::new(),
+                NodeKind::Mission => format!(", shape=hexagon, fillcolor=\"{MISSION_COLOR}\""),
+                NodeKind::Missing => ", style=dashed, color=red".to_string(),
+            };
+            let _ = writeln!(out, "    n{idx} [label=\"{name}\"{style}];");
+        }
+        for edge in &self.edges {
+            let label = dot_escape(&edge.label);
+            let style = match edge.kind {
+                EdgeKind::Choice => "",
+                EdgeKind::Next => ", style=dashed",
+                EdgeKind::Mission => ", style=dotted",
+            };
+            let _ = writeln!(
+                out,
+                "    n{} -> n{} [label=\"{label}\"{style}];",
+                edge.from, edge.to
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn mermaid(&self) -> String {
+        let mut out = String::new();
+        out.push_str("flowchart LR\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let name = mermaid_escape(&node.name);
+            let _ = match node.kind {
+                NodeKind::Mission => writeln!(out, "    n{idx}{{{{\"{name}\"}}}}"),
+                _ => writeln!(out, "    n{idx}[\"{name}\"]"),
+            };
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Choice => "-->",
+                EdgeKind::Next | EdgeKind::Mission => "-.->",
+            };
+            let _ = if edge.label.is_empty() {
+                writeln!(out, "    n{} {arrow} n{}", edge.from, edge.to)
+            } else {
+                let label = mermaid_escape(&edge.label);
+                writeln!(out, "    n{} {arrow}|\"{label}\"| n{}", edge.from, edge.to)
+            };
+        }
+
+        let mut classes: Vec<(String, Vec<usize>)> = Vec::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let class = match node.kind {
+                NodeKind::Scene(Some(day)) => format!("day{day}"),
+                NodeKind::Scene(None) => continue,
+                NodeKind::Mission => "mission".to_string(),
+                NodeKind::Missing => "missing".to_string(),
+            };
+            match classes.iter_mut().find(|(name, _)| *name == class) {
+                Some((_, members)) => members.push(idx),
+                None => classes.push((class, vec![idx])),
+            }
+        }
+        for (class, members) in classes {
+            let style = match class.strip_prefix("day") {
+                Some(day) => format!("fill:{}", day_color(day.parse().unwrap_or(1))),
+                None if class == "mission" => format!("fill:{MISSION_COLOR}"),
+                None => "stroke:red,stroke-dasharray:5 5".to_string(),
+            };
+            let members: Vec<_> = members.iter().map(|idx| format!("n{idx}")).collect();
+            let _ = writeln!(out, "    classDef {class} {style}");
+            let _ = writeln!(out, "    class {} {class}", members.join(","));
+        }
+        out
+    }
+}
+
+fn day_color(day: usize) -> &'static str {
+    DAY_COLORS[day.saturating_sub(1) % DAY_COLORS.len()]
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
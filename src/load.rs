@@ -1,16 +1,190 @@
-use crate::dating_sim::DatingScene;
+//! Scene files are the `*.scenes.json` files in `assets/Scenes/`, they are all loaded
+//! at startup and reloaded when changed. They look like this:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "scenes": [
+//!     {
+//!       "id": "Should we eat",
+//!       "person": "Joe",
+//!       "requires": "alive(Cat)",
+//!       "text": [
+//!         "A line without a speaker is narration",
+//!         ["Carle", "That cat is looking really yummy", "angry"],
+//!         ["Joe", "Don't you dare, {captain}"],
+//!         [null, "So is...{pause 0.5} this one{speed 0.5}, probably"]
+//!       ],
+//!       "choice": [["We should eat the cat", "EAT"], ["We should not", "No eat"]],
+//!       "next": ["Day1Evening"],
+//!       "outcome": [["Foodsupply", 3], ["CatDead", 1]],
+//!       "mission": "Oil"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Only `id` and `text` are required. Speakers and `person` are ids or aliases from
+//! `characters.ron` (like "Twin1"), "You" is the player. A line's third element picks
+//! the speaker's portrait for it, e.g. "angry", and `{captain}` in a line is replaced
+//! by the captain's name. Lines can pause and change speed as they are revealed, see
+//! `typewriter`. When a scene ends the player
+//! picks one of `choice`, otherwise the first playable scene in `next` follows.
+//! `requires` is a condition as described in `condition`. `mission` gives the player a
+//! mission when the scene ends, bringing back what it asks for sets e.g. the
+//! `WaterDelivered` flag. `outcome` adds to flags, `<Name>Favor`, `<Name>Dead` or a
+//! ship store like `Foodsupply`.
+//!
+//! Version 0 files are a bare list of scenes that link with `"scene": [[order, id]]`,
+//! they are migrated when loaded.
+
+use crate::story::DatingScene;
+use anyhow::{bail, Context};
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext, LoadedFolder},
+    prelude::*,
+};
 use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const SCENE_FORMAT_VERSION: u64 = 1;
+
+#[derive(Deserialize)]
+struct SceneFile {
+    version: u64,
+    scenes: Vec<DatingScene>,
+}
+
+/// All scenes of one scene file.
+#[derive(Asset, TypePath, Debug)]
+pub struct SceneLibrary {
+    pub scenes: Vec<DatingScene>,
+}
+
+#[derive(Default)]
+pub struct SceneLoader;
+
+// Keeps every scene file loaded
+#[derive(Resource)]
+struct SceneFolder(Handle<LoadedFolder>);
 
-pub fn load_scenes() -> Vec<DatingScene> {
-    let json_file_path = std::path::Path::new("assets/Scenes/testScene.json");
+impl AssetLoader for SceneLoader {
+    type Asset = SceneLibrary;
+    type Settings = ();
+    type Error = anyhow::Error;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let json = std::str::from_utf8(&bytes)?;
+        let scenes = parse_scenes(json)
+            .with_context(|| format!("failed to load {}", load_context.path().display()))?;
+        Ok(SceneLibrary { scenes })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenes.json"]
+    }
+}
+
+pub fn load_scenes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SceneFolder(asset_server.load_folder("Scenes")));
+}
+
+/// Every scene file in `dir`, sorted by name.
+pub fn scene_files(dir: &std::path::Path) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".scenes.json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
 
-    let file = std::fs::File::open(json_file_path).expect("failed to open file");
+/// Reads every scene file in `dir` without the asset server, keyed by id.
+pub fn read_scenes(dir: &std::path::Path) -> anyhow::Result<HashMap<String, DatingScene>> {
+    let mut scenes = HashMap::new();
+    for path in scene_files(dir)? {
+        let json = std::fs::read_to_string(&path)?;
+        let file_scenes =
+            parse_scenes(&json).with_context(|| format!("failed to load {}", path.display()))?;
+        scenes.extend(
+            file_scenes
+                .into_iter()
+                .map(|scene| (scene.id.clone(), scene)),
+        );
+    }
+    Ok(scenes)
+}
+
+pub fn parse_scenes(json: &str) -> anyhow::Result<Vec<DatingScene>> {
+    // Parse current files straight from the text so errors keep their line numbers
+    if !json.trim_start().starts_with('[') {
+        let file: SceneFile = serde_json::from_str(json)?;
+        if file.version != SCENE_FORMAT_VERSION {
+            bail!("unsupported scene format version {}", file.version);
+        }
+        return Ok(file.scenes);
+    }
+
+    let legacy: Value = serde_json::from_str(json)?;
+    let file: SceneFile = serde_json::from_value(migrate_v0(legacy)?)?;
+    Ok(file.scenes)
+}
+
+fn migrate_v0(scenes: Value) -> anyhow::Result<Value> {
+    let Value::Array(mut scenes) = scenes else {
+        bail!("version 0 scene file must be a list of scenes");
+    };
+
+    for scene in &mut scenes {
+        let Value::Object(scene) = scene else {
+            continue;
+        };
+
+        if let Some(links) = scene.remove("scene") {
+            let mut links: Vec<(i64, String)> =
+                serde_json::from_value(links).context("\"scene\" must be [[order, id]] pairs")?;
+            links.sort_by_key(|(order, _)| *order);
+            let next = links.into_iter().map(|(_, id)| Value::String(id)).collect();
+            scene.insert("next".to_string(), Value::Array(next));
+        }
+    }
+
+    Ok(serde_json::json!({
+        "version": 1,
+        "scenes": scenes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v0_links_become_next_in_order() {
+        let json = r#"[
+            { "id": "Start", "text": ["Hi"], "scene": [[2, "B"], [1, "A"]] },
+            { "id": "A", "text": ["A"] },
+            { "id": "B", "text": ["B"] }
+        ]"#;
+        let scenes = parse_scenes(json).unwrap();
+        assert_eq!(scenes.len(), 3);
+        assert_eq!(scenes[0].next, ["A", "B"]);
+        assert!(scenes[1].next.is_empty());
+    }
 
-    let scenes: Vec<DatingScene> =
-        serde_json::from_reader(file).expect("error while reading or parsing");
-    println!("Testing");
-    for s in &scenes {
-        dbg!(s);
+    #[test]
+    fn bad_scene_files_are_rejected() {
+        let not_a_list = serde_json::json!({ "id": "Start", "text": ["Hi"] });
+        assert!(migrate_v0(not_a_list).is_err());
+        assert!(parse_scenes(r#"[{ "id": "Start", "text": ["Hi"], "scene": ["A"] }]"#).is_err());
+        assert!(parse_scenes(r#"{ "version": 2, "scenes": [] }"#).is_err());
+        assert!(parse_scenes(r#"{ "scenes": [] }"#).is_err());
     }
-    scenes
 }
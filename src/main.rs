@@ -2,9 +2,21 @@
 
 use bevy::prelude::*;
 
+mod condition;
 mod dating_sim;
+mod ending;
+mod eva;
+mod explore;
 mod game;
+mod graph;
 mod load;
+mod roster;
+mod save;
+mod ship;
+mod story;
+mod tiles;
+mod typewriter;
+mod validate;
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum GameState {
@@ -12,19 +24,75 @@ enum GameState {
     Menu,
     Explore,
     DatingSim,
+    Ending,
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let args: Vec<String> = std::env::args().collect();
+    let scene_dir = std::path::Path::new("assets/Scenes");
+    let roster_path = std::path::Path::new("assets").join(roster::ROSTER_PATH);
+    if args.iter().any(|arg| arg == "--validate") {
+        std::process::exit(validate::run(scene_dir, &roster_path));
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--explore") {
+        let max_steps = match args.get(idx + 1) {
+            Some(arg) => match arg.parse() {
+                Ok(steps) => steps,
+                Err(_) => {
+                    eprintln!("bad step count {arg}, use a whole number of steps");
+                    std::process::exit(1);
+                }
+            },
+            None => 40,
+        };
+        std::process::exit(explore::run(scene_dir, &roster_path, max_steps));
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--graph") {
+        let format = args.get(idx + 1).map_or("dot", String::as_str);
+        std::process::exit(graph::run(scene_dir, format));
+    }
+
+    let captain = args
+        .iter()
+        .position(|arg| arg == "--captain")
+        .and_then(|idx| args.get(idx + 1))
+        .cloned();
+    let text_speed = match args.iter().position(|arg| arg == "--text-speed") {
+        Some(idx) => {
+            let arg = args.get(idx + 1).map_or("", String::as_str);
+            match arg.parse::<f32>() {
+                Ok(speed) if speed.is_finite() && speed > 0.0 => Some(speed),
+                _ => {
+                    eprintln!("bad text speed {arg}, use characters per second above 0");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .init_state::<GameState>()
         .add_systems(Startup, setup)
         .add_plugins((
             menu::menu_plugin,
             game::game_plugin,
             dating_sim::dating_sim_plugin,
-        ))
-        .run();
+            eva::eva_plugin,
+            ending::ending_plugin,
+            save::save_plugin,
+        ));
+    app.world_mut()
+        .resource_mut::<story::DatingContext>()
+        .captain = captain.clone();
+    app.insert_resource(story::CaptainName(captain));
+    if let Some(chars_per_second) = text_speed {
+        app.world_mut()
+            .resource_mut::<typewriter::TextSettings>()
+            .chars_per_second = chars_per_second;
+    }
+    app.run();
 }
 
 fn setup(mut commands: Commands) {
@@ -39,10 +107,16 @@ fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands
 
 mod menu {
     use super::GameState;
+    use crate::game::ExplorationProgress;
+    use crate::save::{self, SavedDay};
+    use crate::story::{CaptainName, DatingContext, Phase};
     use bevy::prelude::*;
 
     pub fn menu_plugin(app: &mut App) {
-        app.add_systems(Update, change_scene.run_if(in_state(GameState::Menu)));
+        app.add_systems(
+            Update,
+            (change_scene, load_game).run_if(in_state(GameState::Menu)),
+        );
     }
 
     pub fn change_scene(
@@ -57,4 +131,33 @@ mod menu {
             println!("going exploration mode");
         }
     }
+
+    pub fn load_game(
+        keys: Res<ButtonInput<KeyCode>>,
+        mut menu_state: ResMut<NextState<GameState>>,
+        mut context: ResMut<DatingContext>,
+        mut progress: ResMut<ExplorationProgress>,
+        mut saved_day: ResMut<SavedDay>,
+        captain: Res<CaptainName>,
+    ) {
+        if !keys.just_pressed(KeyCode::KeyL) {
+            return;
+        }
+        let Some(slot) = save::latest_slot() else {
+            println!("no saves in {}", save::save_dir().display());
+            return;
+        };
+        match save::read_slot(&slot) {
+            Ok(save) => {
+                save.apply(&mut context, &mut progress, captain.0.as_ref());
+                saved_day.0 = Some(context.day);
+                menu_state.set(match context.phase {
+                    Phase::Expedition => GameState::Explore,
+                    _ => GameState::DatingSim,
+                });
+                println!("loaded {slot}, day {}", context.day);
+            }
+            Err(err) => error!("Loading failed: {err:#}"),
+        }
+    }
 }
@@ -0,0 +1,222 @@
+//! Who is on the ship, read from `assets/characters.ron`:
+//!
+//! ```ron
+//! (
+//!     characters: [
+//!         (
+//!             id: "Carle",
+//!             name: "Carly",
+//!             aliases: ["Carly"],
+//!             color: "#E07A5F",
+//!             portraits: {
+//!                 "default": "Portraits/Character_Carly.png",
+//!                 "angry": "Portraits/Character_Carly_angry.png",
+//!             },
+//!             favor: 20,
+//!             dialogue: (scene_flag: 4, mission: Some(Oil)),
+//!         ),
+//!         (id: "Main", name: "Captain", aliases: ["You"], crew: false, player: true),
+//!     ],
+//! )
+//! ```
+//!
+//! Scenes, conditions and outcomes can use a character's id or any of their
+//! aliases. `name` is what the player sees. Portraits are keyed by expression,
+//! lines without one or with one the character doesn't have show `default`.
+//! `color` is the hex color of the name plate above their lines.
+//! Characters with `crew: false` can speak but aren't someone to talk to. The one
+//! with `player: true` is the captain, their name can be changed with `--captain`.
+
+use crate::story::{CharactersStatus, DialogueOption};
+use anyhow::{bail, Context};
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+pub const ROSTER_PATH: &str = "characters.ron";
+pub const DEFAULT_EXPRESSION: &str = "default";
+
+/// A character's id as written in `characters.ron`, e.g. `Joe`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(transparent)]
+pub struct CharacterId(pub String);
+
+impl fmt::Display for CharacterId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CharacterInfo {
+    pub id: CharacterId,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub portraits: HashMap<String, String>,
+    #[serde(default)]
+    pub favor: usize,
+    #[serde(default)]
+    pub dialogue: DialogueOption,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default = "default_crew")]
+    pub crew: bool,
+    #[serde(default)]
+    pub player: bool,
+}
+
+fn default_crew() -> bool {
+    true
+}
+
+impl CharacterInfo {
+    /// The portrait for `expression`, falling back to the default one.
+    pub fn portrait(&self, expression: Option<&str>) -> Option<&str> {
+        expression
+            .and_then(|expression| self.portraits.get(expression))
+            .or_else(|| self.portraits.get(DEFAULT_EXPRESSION))
+            .map(String::as_str)
+    }
+
+    /// Name plate color, white without one.
+    pub fn color(&self) -> Color {
+        self.color
+            .as_deref()
+            .and_then(|hex| Srgba::hex(hex).ok())
+            .map_or(Color::WHITE, Color::from)
+    }
+}
+
+#[derive(Deserialize)]
+struct RosterFile {
+    characters: Vec<CharacterInfo>,
+}
+
+#[derive(Asset, TypePath, Clone, Debug, Default)]
+pub struct Roster {
+    characters: Vec<CharacterInfo>,
+    // Ids and aliases
+    by_name: HashMap<String, CharacterId>,
+}
+
+impl Roster {
+    pub fn parse(ron: &str) -> anyhow::Result<Roster> {
+        // Lets `color: "#E07A5F"` leave out the `Some`
+        let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+        let file: RosterFile = options.from_str(ron)?;
+        let mut by_name = HashMap::new();
+
+        for info in &file.characters {
+            if info.id.0.is_empty() {
+                bail!("a character has an empty id");
+            }
+            let names = std::iter::once(&info.id.0).chain(&info.aliases);
+            for name in names {
+                if let Some(other) = by_name.insert(name.clone(), info.id.clone()) {
+                    bail!("{} and {other} are both called {name}", info.id);
+                }
+            }
+            if !info.portraits.is_empty() && !info.portraits.contains_key(DEFAULT_EXPRESSION) {
+                bail!("{} has portraits but no {DEFAULT_EXPRESSION} one", info.id);
+            }
+            if let Some(color) = &info.color {
+                Srgba::hex(color).with_context(|| format!("{} has color {color}", info.id))?;
+            }
+        }
+        let mut players = file.characters.iter().filter(|info| info.player);
+        if let (Some(first), Some(second)) = (players.next(), players.next()) {
+            bail!("{} and {} are both the player", first.id, second.id);
+        }
+        if !file.characters.iter().any(|info| info.crew) {
+            bail!("nobody is in the crew");
+        }
+
+        Ok(Roster {
+            characters: file.characters,
+            by_name,
+        })
+    }
+
+    pub fn characters(&self) -> &[CharacterInfo] {
+        &self.characters
+    }
+
+    pub fn get(&self, id: &CharacterId) -> Option<&CharacterInfo> {
+        self.characters.iter().find(|info| info.id == *id)
+    }
+
+    /// The character with this id or alias.
+    pub fn find(&self, name: &str) -> Option<&CharacterId> {
+        self.by_name.get(name)
+    }
+
+    /// What the player sees as the character's name.
+    pub fn name<'a>(&'a self, id: &'a CharacterId) -> &'a str {
+        self.get(id).map_or(&id.0, |info| &info.name)
+    }
+
+    /// The captain, who "You" lines belong to.
+    pub fn player(&self) -> Option<&CharacterInfo> {
+        self.characters.iter().find(|info| info.player)
+    }
+
+    /// The crew as they are at the start of a run.
+    pub fn crew(&self) -> Vec<CharactersStatus> {
+        self.characters
+            .iter()
+            .filter(|info| info.crew)
+            .map(|info| CharactersStatus {
+                character: info.id.clone(),
+                current_dialogue: info.dialogue.clone(),
+                favor: info.favor,
+                alive: true,
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct RosterLoader;
+
+// Keeps the roster loaded
+#[derive(Resource)]
+struct RosterHandle(Handle<Roster>);
+
+impl AssetLoader for RosterLoader {
+    type Asset = Roster;
+    type Settings = ();
+    type Error = anyhow::Error;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let ron = std::str::from_utf8(&bytes)?;
+        Roster::parse(ron)
+            .with_context(|| format!("failed to load {}", load_context.path().display()))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["characters.ron"]
+    }
+}
+
+pub fn load_roster(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RosterHandle(asset_server.load(ROSTER_PATH)));
+}
+
+/// Reads the roster without the asset server.
+pub fn read_roster(path: &std::path::Path) -> anyhow::Result<Roster> {
+    let ron = std::fs::read_to_string(path)?;
+    Roster::parse(&ron).with_context(|| format!("failed to load {}", path.display()))
+}
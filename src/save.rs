@@ -0,0 +1,306 @@
+//! Save slots are json files in the user data directory, e.g.
+//! `~/.local/share/gamejam/saves/autosave.json` on Linux. The game autosaves whenever
+//! a new day starts and F5 quicksaves, the menu loads the newest slot.
+
+use crate::game::{ExplorationProgress, Inventory};
+use crate::roster::CharacterId;
+use crate::ship::ShipResources;
+use crate::story::{DatingContext, LoggedLine, Mission, MissionType, Phase};
+use anyhow::{bail, Context};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+pub const SAVE_FORMAT_VERSION: u64 = 3;
+pub const AUTOSAVE: &str = "autosave";
+pub const QUICKSAVE: &str = "quicksave";
+
+/// Everything that has to survive a restart. The current conversation isn't saved,
+/// loading always lands between scenes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveData {
+    pub version: u64,
+    pub day: usize,
+    #[serde(default)]
+    pub phase: Phase,
+    pub characters: Vec<SavedCharacter>,
+    pub flags: Vec<(String, isize)>,
+    pub seen_scenes: Vec<String>,
+    pub gathered_mission: Vec<Mission>,
+    pub player_position: Option<[f32; 2]>,
+    #[serde(default)]
+    pub dug_tiles: Vec<[u32; 2]>,
+    #[serde(default)]
+    pub inventory: Vec<(MissionType, usize)>,
+    #[serde(default)]
+    pub ship: ShipResources,
+    #[serde(default)]
+    pub captain: Option<String>,
+    pub history: Vec<LoggedLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedCharacter {
+    pub character: CharacterId,
+    pub favor: usize,
+    pub alive: bool,
+}
+
+// Day of the last save or load, the first day seen isn't saved so starting a new
+// game doesn't overwrite the autosave
+#[derive(Resource, Default)]
+pub struct SavedDay(pub Option<usize>);
+
+pub fn save_plugin(app: &mut App) {
+    app.init_resource::<SavedDay>()
+        .add_systems(Update, (autosave, quicksave));
+}
+
+impl SaveData {
+    pub fn new(context: &DatingContext, progress: &ExplorationProgress) -> SaveData {
+        SaveData {
+            version: SAVE_FORMAT_VERSION,
+            day: context.day,
+            phase: context.phase,
+            characters: context
+                .all_characters
+                .iter()
+                .map(|status| SavedCharacter {
+                    character: status.character.clone(),
+                    favor: status.favor,
+                    alive: status.alive,
+                })
+                .collect(),
+            flags: context.flags.clone(),
+            seen_scenes: context.seen_scenes.clone(),
+            gathered_mission: context.gathered_mission.clone(),
+            player_position: progress.position.map(|position| position.to_array()),
+            dug_tiles: progress.dug.iter().map(|tile| tile.to_array()).collect(),
+            inventory: progress
+                .inventory
+                .goods
+                .iter()
+                .map(|(item, count)| (*item, *count))
+                .collect(),
+            ship: context.ship.clone(),
+            captain: context.captain.clone(),
+            history: context.history.clone(),
+        }
+    }
+
+    /// Replaces the progress of a running game with this save, the scenes stay loaded.
+    /// `captain` is the name given on the command line, if any.
+    pub fn apply(
+        self,
+        context: &mut DatingContext,
+        progress: &mut ExplorationProgress,
+        captain: Option<&String>,
+    ) {
+        *context = DatingContext {
+            scenes: context.scenes.clone(),
+            captain: captain.cloned().or(self.captain),
+            ..DatingContext::new(HashMap::new(), context.roster.clone())
+        };
+        context.day = self.day;
+        context.phase = self.phase;
+        context.flags = self.flags;
+        context.seen_scenes = self.seen_scenes;
+        context.gathered_mission = self.gathered_mission;
+        context.ship = self.ship;
+        context.history = self.history;
+        for saved in self.characters {
+            match context.character_mut(&saved.character) {
+                Some(status) => {
+                    status.favor = saved.favor;
+                    status.alive = saved.alive;
+                }
+                None => warn!("Save has {} who isn't in the crew", saved.character),
+            }
+        }
+        *progress = ExplorationProgress {
+            position: self.player_position.map(Vec2::from_array),
+            dug: self.dug_tiles.into_iter().map(UVec2::from_array).collect(),
+            inventory: Inventory {
+                goods: self.inventory.into_iter().collect(),
+            },
+        };
+    }
+}
+
+/// Where save slots live, following each platform's place for user data.
+pub fn save_dir() -> PathBuf {
+    let env = |name| std::env::var_os(name).map(PathBuf::from);
+    let data = if cfg!(windows) {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+    };
+    data.unwrap_or_default().join("gamejam").join("saves")
+}
+
+fn slot_path(slot: &str) -> PathBuf {
+    save_dir().join(format!("{slot}.json"))
+}
+
+pub fn write_slot(slot: &str, save: &SaveData) -> anyhow::Result<()> {
+    let path = slot_path(slot);
+    std::fs::create_dir_all(save_dir())?;
+    let json = serde_json::to_string_pretty(save)?;
+    std::fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read_slot(slot: &str) -> anyhow::Result<SaveData> {
+    let path = slot_path(slot);
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let save = migrate(serde_json::from_str(&json)?)
+        .with_context(|| format!("failed to load {}", path.display()))?;
+    Ok(serde_json::from_value(save)?)
+}
+
+/// The most recently written slot, if there are any saves.
+pub fn latest_slot() -> Option<String> {
+    let entries = std::fs::read_dir(save_dir()).ok()?;
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let modified = path.metadata().ok()?.modified().ok()?;
+            Some((modified, path.file_stem()?.to_string_lossy().into_owned()))
+        })
+        .max()
+        .map(|(_, slot)| slot)
+}
+
+// Brings older saves up to the current version one step at a time, every format
+// change adds an arm here
+fn migrate(mut save: Value) -> anyhow::Result<Value> {
+    loop {
+        let Some(version) = save.get("version").and_then(Value::as_u64) else {
+            bail!("save has no version");
+        };
+        save = match version {
+            SAVE_FORMAT_VERSION => return Ok(save),
+            newer if newer > SAVE_FORMAT_VERSION => {
+                bail!("save is from a newer version of the game ({newer})")
+            }
+            1 => migrate_v1(save)?,
+            2 => migrate_v2(save)?,
+            older => bail!("unsupported save version {older}"),
+        };
+    }
+}
+
+// Version 2 remembers who asked for each mission
+fn migrate_v1(mut save: Value) -> anyhow::Result<Value> {
+    let Some(missions) = save
+        .get_mut("gathered_mission")
+        .and_then(Value::as_array_mut)
+    else {
+        bail!("save has no gathered_mission list");
+    };
+    for mission in missions {
+        *mission = serde_json::json!({ "kind": mission.take(), "from": null });
+    }
+    save["version"] = 2.into();
+    Ok(save)
+}
+
+// Version 3 keeps the dialogue history, older saves start with an empty one
+fn migrate_v2(mut save: Value) -> anyhow::Result<Value> {
+    save["history"] = Value::Array(vec![]);
+    save["version"] = 3.into();
+    Ok(save)
+}
+
+fn save_to(slot: &str, context: &DatingContext, progress: &ExplorationProgress) {
+    match write_slot(slot, &SaveData::new(context, progress)) {
+        Ok(()) => info!("Saved day {} to {slot}", context.day),
+        Err(err) => error!("Saving failed: {err:#}"),
+    }
+}
+
+fn autosave(
+    context: Res<DatingContext>,
+    progress: Res<ExplorationProgress>,
+    mut saved_day: ResMut<SavedDay>,
+) {
+    match saved_day.0 {
+        Some(day) if day == context.day => {}
+        Some(_) => {
+            saved_day.0 = Some(context.day);
+            save_to(AUTOSAVE, &context, &progress);
+        }
+        None => saved_day.0 = Some(context.day),
+    }
+}
+
+fn quicksave(
+    keys: Res<ButtonInput<KeyCode>>,
+    context: Res<DatingContext>,
+    progress: Res<ExplorationProgress>,
+) {
+    if keys.just_pressed(KeyCode::F5) {
+        save_to(QUICKSAVE, &context, &progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Written by the first version with save slots
+    const V1_SAVE: &str = r#"{
+        "version": 1,
+        "day": 3,
+        "characters": [
+            { "character": "Joe", "favor": 25, "alive": true },
+            { "character": "Cat", "favor": 20, "alive": false }
+        ],
+        "flags": [["CatDead", 1]],
+        "seen_scenes": ["Day1Morning", "EAT"],
+        "gathered_mission": ["Oil", "Tutorial"],
+        "player_position": [120.0, -40.0]
+    }"#;
+
+    #[test]
+    fn v1_saves_migrate_to_the_current_format() {
+        let save = migrate(serde_json::from_str(V1_SAVE).unwrap()).unwrap();
+        let save: SaveData = serde_json::from_value(save).unwrap();
+        assert_eq!(save.version, SAVE_FORMAT_VERSION);
+        assert_eq!(save.day, 3);
+        assert_eq!(save.phase, Phase::Morning);
+        assert_eq!(
+            save.gathered_mission,
+            [
+                Mission {
+                    kind: MissionType::Oil,
+                    from: None,
+                },
+                Mission {
+                    kind: MissionType::Tutorial,
+                    from: None,
+                },
+            ]
+        );
+        assert!(save.history.is_empty());
+        assert_eq!(save.player_position, Some([120.0, -40.0]));
+        assert_eq!(save.characters[1].character.0, "Cat");
+        assert!(!save.characters[1].alive);
+        assert_eq!(save.ship, ShipResources::default());
+    }
+
+    #[test]
+    fn saves_without_a_known_version_are_rejected() {
+        let newer = serde_json::json!({ "version": SAVE_FORMAT_VERSION + 1 });
+        assert!(migrate(newer).is_err());
+        assert!(migrate(serde_json::json!({ "version": 0 })).is_err());
+        assert!(migrate(serde_json::json!({ "day": 1 })).is_err());
+    }
+}
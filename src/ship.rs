@@ -0,0 +1,118 @@
+//! The ship's stores of oxygen, food and reactor power, counted in days they last.
+//!
+//! A day on the planet passes a week on the ship's clock. Oxygen goes faster every
+//! day as contaminants build up in the filters, and food goes twice as fast once
+//! the greenhouse prefab is overdue. Outcomes like `["Foodsupply", 3]` add to a
+//! store and ice and oil brought back from outside top up oxygen and power.
+//!
+//! A store at or below two weeks plays its alert scene, e.g. `OxygenLow`, at the
+//! next chance. Every day that ends with a store empty someone in the crew dies.
+
+use crate::story::{self, MissionType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// A day on the planet in ship days
+const SHIP_DAYS_PER_DAY: isize = 7;
+// Extra oxygen used per day for every day that has passed
+const CONTAMINANT_BUILDUP: isize = 2;
+// Set by the scene where the greenhouse prefab gets put up
+pub const GREENHOUSE_FLAG: &str = "GreenhouseBuilt";
+// Without the greenhouse food goes twice as fast from this day on
+const GREENHOUSE_DEADLINE: usize = 3;
+// Days of supply left when the alert scene plays
+const LOW_SUPPLY: isize = 14;
+// Days of supply each good brought back adds
+const GOODS_SUPPLY: [(MissionType, Supply, isize); 2] = [
+    (MissionType::Water, Supply::Oxygen, 2),
+    (MissionType::Oil, Supply::Power, 2),
+];
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Supply {
+    Oxygen,
+    Food,
+    Power,
+}
+
+impl Supply {
+    pub const ALL: [Supply; 3] = [Supply::Oxygen, Supply::Food, Supply::Power];
+
+    pub fn from_name(name: &str) -> Option<Supply> {
+        story::from_name(name)
+    }
+
+    // Played when the store runs low, e.g. "OxygenLow"
+    pub fn alert_scene(self) -> String {
+        format!("{self:?}Low")
+    }
+}
+
+/// Days left of each store, as the crew counted them on the first day.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShipResources {
+    pub oxygen: isize,
+    pub food: isize,
+    pub power: isize,
+}
+
+impl Default for ShipResources {
+    fn default() -> Self {
+        ShipResources {
+            oxygen: 60,
+            food: 120,
+            power: 60,
+        }
+    }
+}
+
+impl ShipResources {
+    pub fn get(&self, supply: Supply) -> isize {
+        match supply {
+            Supply::Oxygen => self.oxygen,
+            Supply::Food => self.food,
+            Supply::Power => self.power,
+        }
+    }
+
+    /// Changes a store, it never drops below empty.
+    pub fn add(&mut self, supply: Supply, days: isize) {
+        let store = match supply {
+            Supply::Oxygen => &mut self.oxygen,
+            Supply::Food => &mut self.food,
+            Supply::Power => &mut self.power,
+        };
+        *store = store.saturating_add(days).max(0);
+    }
+
+    pub fn is_low(&self, supply: Supply) -> bool {
+        self.get(supply) <= LOW_SUPPLY
+    }
+
+    /// Uses up what the ship needs for `day`, returns the stores that ran out.
+    pub fn end_day(&mut self, day: usize, greenhouse: bool) -> Vec<Supply> {
+        let contaminants = CONTAMINANT_BUILDUP * (day as isize - 1).max(0);
+        let food = if greenhouse || day < GREENHOUSE_DEADLINE {
+            SHIP_DAYS_PER_DAY
+        } else {
+            SHIP_DAYS_PER_DAY * 2
+        };
+        self.add(Supply::Oxygen, -(SHIP_DAYS_PER_DAY + contaminants));
+        self.add(Supply::Food, -food);
+        self.add(Supply::Power, -SHIP_DAYS_PER_DAY);
+
+        Supply::ALL
+            .into_iter()
+            .filter(|supply| self.get(*supply) == 0)
+            .collect()
+    }
+
+    /// Turns goods brought back from outside into supplies, they are used up.
+    pub fn stock(&mut self, goods: &mut HashMap<MissionType, usize>) {
+        for (item, supply, days) in GOODS_SUPPLY {
+            if let Some(count) = goods.remove(&item) {
+                self.add(supply, days * count as isize);
+            }
+        }
+    }
+}
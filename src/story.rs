@@ -0,0 +1,902 @@
+//! The story side of the dating sim: who is alive, what has been said and which
+//! scene plays next. Nothing in here renders, so whole playthroughs can be run
+//! with `DatingContext::simulate`.
+
+use crate::condition::{Condition, ConditionContext};
+use crate::roster::{CharacterId, Roster};
+use crate::ship::{ShipResources, Supply, GREENHOUSE_FLAG};
+use crate::typewriter;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum MissionType {
+    Tutorial,
+    Water,
+    Explore,
+    Oil,
+    Iron,
+}
+
+impl MissionType {
+    pub const ALL: [MissionType; 5] = [
+        MissionType::Tutorial,
+        MissionType::Water,
+        MissionType::Explore,
+        MissionType::Oil,
+        MissionType::Iron,
+    ];
+
+    pub fn from_name(name: &str) -> Option<MissionType> {
+        from_name(name)
+    }
+
+    // The rest only need someone to go outside
+    fn needs_goods(self) -> bool {
+        matches!(
+            self,
+            MissionType::Water | MissionType::Oil | MissionType::Iron
+        )
+    }
+
+    // Set when one of these missions is fulfilled, e.g. "WaterDelivered"
+    pub fn delivered_flag(self) -> String {
+        format!("{self:?}Delivered")
+    }
+}
+
+/// A mission someone asked for in a scene, open until an expedition fulfils it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Mission {
+    pub kind: MissionType,
+    pub from: Option<CharacterId>,
+}
+
+/// A line the player read or a choice they took, kept for the dialogue history.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LoggedLine {
+    pub day: usize,
+    pub speaker: Option<CharacterId>,
+    // Without markup
+    pub text: String,
+    #[serde(default)]
+    pub choice: bool,
+}
+
+// Goods a mission asks for and the favor it earns with whoever asked
+pub const GOODS_PER_MISSION: usize = 3;
+pub const MISSION_FAVOR: usize = 5;
+
+/// Flags the game sets on its own, besides the ones in scene outcomes.
+pub fn game_flags() -> Vec<String> {
+    let mut flags: Vec<_> = MissionType::ALL
+        .iter()
+        .map(|mission| mission.delivered_flag())
+        .collect();
+    flags.push(crate::eva::OVERTIME_FLAG.to_string());
+    flags
+}
+
+// Looks a name up the same way scene files are read
+pub(crate) fn from_name<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+    use serde::de::{value::StrDeserializer, IntoDeserializer};
+    let deserializer: StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+#[derive(Clone)]
+pub struct CharactersStatus {
+    pub character: CharacterId,
+    pub current_dialogue: DialogueOption,
+    pub favor: usize,
+    pub alive: bool,
+}
+
+/// The captain's name given with `--captain`, it wins over the one in a save.
+#[derive(Resource, Default)]
+pub struct CaptainName(pub Option<String>);
+
+#[derive(Resource, Clone)]
+pub struct DatingContext {
+    pub all_characters: Vec<CharactersStatus>,
+    pub day: usize,
+    pub phase: Phase,
+    // The run ends after the evening of this day
+    pub days: usize,
+    pub cursor: isize,
+    pub choice_cursor: usize,
+    pub selected_scene: DatingScene,
+    // Whose conversation the current scene is part of
+    pub talking_to: Option<CharacterId>,
+    // Only a scene picked by talking to someone can be left halfway, it plays
+    // again the next time. Scheduled and linked scenes would be lost.
+    pub can_leave: bool,
+    pub line: usize,
+    pub scenes: Arc<HashMap<String, DatingScene>>,
+    pub roster: Arc<Roster>,
+    pub seen_scenes: Vec<String>,
+    pub flags: Vec<(String, isize)>,
+    // Open missions
+    pub gathered_mission: Vec<Mission>,
+    pub ship: ShipResources,
+    // Replaces the player's name from the roster
+    pub captain: Option<String>,
+    // Every line shown so far, oldest first
+    pub history: Vec<LoggedLine>,
+}
+
+const MAX_SKIPPED_LINES: usize = 10_000;
+
+pub const DAYS: usize = 7;
+
+/// Plays once the run is over. Its `next` lists the endings, the first one whose
+/// `requires` holds is picked.
+pub const ENDING_SCENE: &str = "Ending";
+
+/// Part of the day. The crew talks in the morning and evening, in between the
+/// player is outside.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Phase {
+    #[default]
+    Morning,
+    Expedition,
+    Evening,
+    /// The last day is over or the whole crew is dead
+    Over,
+}
+
+/// Where the conversation is after a step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    /// A line is showing, see `DatingContext::current_line`
+    Line,
+    /// Waiting for `DatingContext::choose`
+    Choosing,
+    /// Nobody is talking
+    Finished,
+}
+
+/// Scripted player input for `DatingContext::simulate`.
+#[derive(Clone, Debug)]
+pub enum Input {
+    /// Talk to a character, like picking their portrait
+    Talk(CharacterId),
+    /// Start a scene by id
+    Play(String),
+    /// Show the next line
+    Advance,
+    /// Advance until there is a choice or the conversation ends
+    Skip,
+    Choose(usize),
+    /// Move on to the next part of the day
+    NextPhase,
+}
+
+impl DatingContext {
+    pub fn new(scenes: HashMap<String, DatingScene>, roster: Arc<Roster>) -> DatingContext {
+        DatingContext {
+            all_characters: roster.crew(),
+            day: 1,
+            phase: Phase::Morning,
+            days: DAYS,
+            cursor: 2,
+            choice_cursor: 0,
+            selected_scene: DatingScene {
+                id: "1".to_string(),
+                person: None,
+                text: vec![
+                    DialogueLine::narration("This is a placeholder"),
+                    DialogueLine::narration("This is a second placeholder"),
+                ],
+                outcome: None,
+                choice: vec![],
+                next: vec![],
+                mission: None,
+                requires: None,
+            },
+            talking_to: None,
+            can_leave: false,
+            line: 0,
+            scenes: Arc::new(scenes),
+            roster,
+            seen_scenes: vec![],
+            flags: vec![],
+            gathered_mission: vec![],
+            ship: ShipResources::default(),
+            captain: None,
+            history: vec![],
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> isize {
+        self.flags
+            .iter()
+            .find(|(flag, _)| flag == name)
+            .map_or(0, |(_, value)| *value)
+    }
+
+    pub fn add_flag(&mut self, name: &str, value: isize) {
+        match self.flags.iter_mut().find(|(flag, _)| flag == name) {
+            Some((_, old)) => {
+                *old = old.checked_add(value).unwrap_or_else(|| {
+                    warn!("Flag {name} overflowed");
+                    if value > 0 {
+                        isize::MAX
+                    } else {
+                        isize::MIN
+                    }
+                })
+            }
+            None => self.flags.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn character(&self, character: &CharacterId) -> Option<&CharactersStatus> {
+        self.all_characters
+            .iter()
+            .find(|status| status.character == *character)
+    }
+
+    /// The crew members still alive, in roster order.
+    pub fn alive_crew(&self) -> impl Iterator<Item = &CharactersStatus> {
+        self.all_characters.iter().filter(|status| status.alive)
+    }
+
+    pub fn character_mut(&mut self, character: &CharacterId) -> Option<&mut CharactersStatus> {
+        self.all_characters
+            .iter_mut()
+            .find(|status| status.character == *character)
+    }
+
+    /// The character a scene calls `name`, by id or alias.
+    pub fn find_character(&self, name: &str) -> Option<CharacterId> {
+        self.roster.find(name).cloned()
+    }
+
+    /// Swaps in a changed roster, everyone still in it keeps their favor and life.
+    pub fn set_roster(&mut self, roster: Arc<Roster>) {
+        let mut crew = roster.crew();
+        for status in &mut crew {
+            if let Some(old) = self.character(&status.character) {
+                status.favor = old.favor;
+                status.alive = old.alive;
+            }
+        }
+        self.all_characters = crew;
+        self.roster = roster;
+    }
+
+    // "<Name>Favor" changes that character's favor, "<Name>Dead" kills them,
+    // "<Store>supply" changes a ship store, everything else is a plain flag.
+    pub fn apply_outcome(&mut self, outcome: &[(String, isize)]) {
+        for (name, value) in outcome {
+            if let Some(supply) = name.strip_suffix("supply").and_then(Supply::from_name) {
+                self.ship.add(supply, *value);
+                continue;
+            }
+
+            if let Some(character) = name
+                .strip_suffix("Favor")
+                .and_then(|name| self.find_character(name))
+            {
+                match self.character_mut(&character) {
+                    Some(status) => status.favor = status.favor.saturating_add_signed(*value),
+                    None => warn!("Outcome {name} targets a character not in the crew"),
+                }
+                continue;
+            }
+
+            self.add_flag(name, *value);
+            if let Some(character) = name
+                .strip_suffix("Dead")
+                .and_then(|name| self.find_character(name))
+            {
+                let alive = self.flag(name) <= 0;
+                if let Some(status) = self.character_mut(&character) {
+                    status.alive = alive;
+                }
+            }
+        }
+
+        // Nobody left to wait for the next day
+        let crew_dead = self.all_characters.iter().all(|status| !status.alive);
+        if crew_dead && !self.all_characters.is_empty() {
+            self.phase = Phase::Over;
+        }
+    }
+
+    pub fn scene(&self, id: &str) -> Option<&DatingScene> {
+        self.scenes.get(id)
+    }
+
+    pub fn is_playable(&self, scene: &DatingScene) -> bool {
+        scene
+            .requires
+            .as_ref()
+            .is_none_or(|condition| condition.eval(self))
+    }
+
+    // First scene linked with `next` that can be played right now
+    fn next_scene(&self) -> Option<&DatingScene> {
+        self.selected_scene
+            .next
+            .iter()
+            .filter_map(|id| self.scene(id))
+            .find(|scene| self.is_playable(scene))
+    }
+
+    /// Starts the first new scene `character` has to say, if any.
+    pub fn talk_to(&mut self, character: &CharacterId) -> bool {
+        if self
+            .character(character)
+            .is_some_and(|status| !status.alive)
+        {
+            return false;
+        }
+        let mut candidates: Vec<_> = self
+            .scenes
+            .values()
+            .filter(|scene| self.person(scene).as_ref() == Some(character))
+            .filter(|scene| !self.seen_scenes.contains(&scene.id))
+            .filter(|scene| self.is_playable(scene))
+            .map(|scene| scene.id.clone())
+            .collect();
+        candidates.sort();
+        let started = candidates
+            .first()
+            .is_some_and(|id| self.start_scene(&id.clone()));
+        self.can_leave = started;
+        started
+    }
+
+    /// Morning, expedition, evening, then the morning of the next day until the
+    /// last evening is over. `false` once the run has ended.
+    pub fn next_phase(&mut self) -> bool {
+        self.phase = match self.phase {
+            Phase::Morning => Phase::Expedition,
+            Phase::Expedition => Phase::Evening,
+            Phase::Evening if self.day >= self.days => Phase::Over,
+            Phase::Evening => {
+                self.end_day();
+                if self.phase == Phase::Over {
+                    return true;
+                }
+                self.day += 1;
+                Phase::Morning
+            }
+            Phase::Over => return false,
+        };
+        true
+    }
+
+    // The ship uses up its stores overnight, for every empty one the crew member
+    // with the least favor doesn't wake up
+    fn end_day(&mut self) {
+        let greenhouse = self.flag(GREENHOUSE_FLAG) > 0;
+        for supply in self.ship.end_day(self.day, greenhouse) {
+            let victim = self
+                .all_characters
+                .iter()
+                .filter(|status| status.alive)
+                .min_by_key(|status| status.favor)
+                .map(|status| status.character.clone());
+            if let Some(victim) = victim {
+                warn!("{supply:?} ran out, {victim} died");
+                self.apply_outcome(&[(format!("{victim}Dead"), 1)]);
+            }
+        }
+    }
+
+    /// The scene scheduled for this part of the day, like "Day2Evening", if it
+    /// hasn't played yet. Alerts about low ship stores come first, once the run is
+    /// over it's the ending.
+    pub fn scheduled_scene(&self) -> Option<String> {
+        let day_scene = match self.phase {
+            Phase::Morning | Phase::Evening => format!("Day{}{:?}", self.day, self.phase),
+            Phase::Expedition => return None,
+            Phase::Over => ENDING_SCENE.to_string(),
+        };
+        let alerts = Supply::ALL
+            .into_iter()
+            .filter(|supply| self.phase != Phase::Over && self.ship.is_low(*supply))
+            .map(Supply::alert_scene);
+        alerts
+            .chain(std::iter::once(day_scene))
+            .filter_map(|id| self.scene(&id))
+            .filter(|scene| !self.seen_scenes.contains(&scene.id))
+            .find(|scene| self.is_playable(scene))
+            .map(|scene| scene.id.clone())
+    }
+
+    /// The ending picked by the ending scene, once it has played.
+    pub fn ending(&self) -> Option<&DatingScene> {
+        self.scene(ENDING_SCENE)?
+            .next
+            .iter()
+            .filter(|id| self.seen_scenes.contains(id))
+            .find_map(|id| self.scene(id))
+    }
+
+    /// Starts a new conversation with a scene nobody in particular has to say.
+    pub fn play(&mut self, id: &str) -> bool {
+        self.talking_to = None;
+        self.start_scene(id)
+    }
+
+    pub fn start_scene(&mut self, id: &str) -> bool {
+        let Some(scene) = self.scene(id).filter(|scene| self.is_playable(scene)) else {
+            return false;
+        };
+        self.selected_scene = scene.clone();
+        self.can_leave = false;
+        let person = self.person(&self.selected_scene);
+        self.talking_to = person.or(self.talking_to.take());
+        self.line = 0;
+        true
+    }
+
+    /// Who has to say the scene, if it's someone's.
+    pub fn person(&self, scene: &DatingScene) -> Option<CharacterId> {
+        self.find_character(scene.person.as_deref()?)
+    }
+
+    /// Who says the line, `None` for narration.
+    pub fn speaker(&self, line: &DialogueLine) -> Option<CharacterId> {
+        self.find_character(line.speaker.as_deref()?)
+    }
+
+    /// What the player is called.
+    pub fn captain_name(&self) -> &str {
+        self.captain
+            .as_deref()
+            .or(self.roster.player().map(|info| info.name.as_str()))
+            .unwrap_or("Captain")
+    }
+
+    /// The name shown for a character, the captain's can be changed.
+    pub fn display_name<'a>(&'a self, id: &'a CharacterId) -> &'a str {
+        match self.roster.get(id) {
+            Some(info) if info.player => self.captain_name(),
+            _ => self.roster.name(id),
+        }
+    }
+
+    /// The text of a line with `{captain}` replaced by the captain's name.
+    pub fn line_text(&self, line: &DialogueLine) -> String {
+        line.line.replace("{captain}", self.captain_name())
+    }
+
+    // Adds the current line to the history once the player moves past it, so a
+    // scene left halfway and started again doesn't log its lines twice
+    fn log_line(&mut self) {
+        let Some(line) = self.current_line() else {
+            return;
+        };
+        let text = self.line_text(line);
+        let text = typewriter::parse(&text).map_or(text, |(text, _)| text);
+        self.history.push(LoggedLine {
+            day: self.day,
+            speaker: self.speaker(line),
+            text,
+            choice: false,
+        });
+    }
+
+    pub fn current_line(&self) -> Option<&DialogueLine> {
+        self.selected_scene.text.get(self.line)
+    }
+
+    /// Moves to the next line, finishing the scene after the last one.
+    pub fn advance(&mut self) -> Step {
+        self.log_line();
+        self.line += 1;
+        if self.line < self.selected_scene.text.len() {
+            return Step::Line;
+        }
+
+        //We have finished reading
+        // Only now, a scene left halfway plays again
+        let id = &self.selected_scene.id;
+        if !self.seen_scenes.contains(id) {
+            self.seen_scenes.push(id.clone());
+        }
+        if let Some(kind) = self.selected_scene.mission {
+            self.gathered_mission.push(Mission {
+                kind,
+                from: self.talking_to.clone(),
+            });
+        }
+        if let Some(outcome) = self.selected_scene.outcome.clone() {
+            self.apply_outcome(&outcome);
+        }
+
+        if !self.selected_scene.choice.is_empty() {
+            return Step::Choosing;
+        }
+        match self.next_scene().map(|scene| scene.id.clone()) {
+            Some(id) if self.start_scene(&id) => Step::Line,
+            _ => {
+                self.talking_to = None;
+                Step::Finished
+            }
+        }
+    }
+
+    /// Fulfils open missions with the goods brought back from an expedition and
+    /// takes what they use up. Returns the missions that are done now.
+    pub fn deliver(&mut self, goods: &mut HashMap<MissionType, usize>) -> Vec<Mission> {
+        let (mut done, mut open) = (Vec::new(), Vec::new());
+        for mission in std::mem::take(&mut self.gathered_mission) {
+            let fulfilled = !mission.kind.needs_goods()
+                || match goods.get_mut(&mission.kind) {
+                    Some(count) if *count >= GOODS_PER_MISSION => {
+                        *count -= GOODS_PER_MISSION;
+                        true
+                    }
+                    _ => false,
+                };
+            if fulfilled {
+                done.push(mission);
+            } else {
+                open.push(mission);
+            }
+        }
+        self.gathered_mission = open;
+        goods.retain(|_, count| *count > 0);
+
+        for mission in &done {
+            self.add_flag(&mission.kind.delivered_flag(), 1);
+            let from = mission.from.as_ref();
+            if let Some(status) = from.and_then(|from| self.character_mut(from)) {
+                status.favor = status.favor.saturating_add(MISSION_FAVOR);
+            }
+        }
+        done
+    }
+
+    /// Labels of the current choices and whether they can be picked.
+    pub fn choices(&self) -> Vec<(String, bool)> {
+        self.selected_scene
+            .choice
+            .iter()
+            .map(|(label, target)| {
+                let playable = self
+                    .scene(target)
+                    .is_some_and(|scene| self.is_playable(scene));
+                (label.clone(), playable)
+            })
+            .collect()
+    }
+
+    pub fn choose(&mut self, choice: usize) -> bool {
+        let Some((label, target)) = self.selected_scene.choice.get(choice).cloned() else {
+            return false;
+        };
+        if !self.start_scene(&target) {
+            return false;
+        }
+        self.history.push(LoggedLine {
+            day: self.day,
+            speaker: None,
+            text: label,
+            choice: true,
+        });
+        true
+    }
+
+    /// Plays `inputs` without any rendering, stopping at the first one that
+    /// doesn't make sense at that point.
+    pub fn simulate(&mut self, inputs: &[Input]) -> Result<Step, String> {
+        let mut step = Step::Finished;
+        for (idx, input) in inputs.iter().enumerate() {
+            step = self
+                .apply(step, input)
+                .ok_or_else(|| format!("input {idx}: can't {input:?} at {step:?}"))?;
+        }
+        Ok(step)
+    }
+
+    /// Plays a single input at `step`, `None` if it isn't possible there.
+    pub fn apply(&mut self, step: Step, input: &Input) -> Option<Step> {
+        let next = match (input, step) {
+            (Input::Talk(character), Step::Finished) if self.talk_to(character) => Step::Line,
+            (Input::Play(id), Step::Finished) if self.play(id) => Step::Line,
+            (Input::Advance, Step::Line) => self.advance(),
+            // Stays at Step::Line if scenes keep linking to each other forever
+            (Input::Skip, Step::Line) => (0..MAX_SKIPPED_LINES)
+                .map(|_| self.advance())
+                .find(|step| *step != Step::Line)
+                .unwrap_or(Step::Line),
+            (Input::Choose(choice), Step::Choosing) if self.choose(*choice) => Step::Line,
+            (Input::NextPhase, Step::Finished) if self.next_phase() => Step::Finished,
+            _ => return None,
+        };
+        Some(next)
+    }
+}
+
+impl ConditionContext for DatingContext {
+    fn flag(&self, name: &str) -> isize {
+        DatingContext::flag(self, name)
+    }
+
+    fn favor(&self, character: &str) -> Option<isize> {
+        let status = self.character(self.roster.find(character)?)?;
+        Some(status.favor as isize)
+    }
+
+    fn alive(&self, character: &str) -> Option<bool> {
+        let status = self.character(self.roster.find(character)?)?;
+        Some(status.alive)
+    }
+
+    fn day(&self) -> isize {
+        self.day as isize
+    }
+
+    fn missions(&self, mission: &str) -> Option<isize> {
+        let mission = MissionType::from_name(mission)?;
+        let count = self
+            .gathered_mission
+            .iter()
+            .filter(|gathered| gathered.kind == mission)
+            .count();
+        Some(count as isize)
+    }
+
+    fn supply(&self, supply: &str) -> Option<isize> {
+        Some(self.ship.get(Supply::from_name(supply)?))
+    }
+}
+
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct DialogueOption {
+    pub scene_flag: usize,
+    pub mission: Option<MissionType>,
+}
+
+/// One scene of the scene file format, see `load` for the file layout.
+#[derive(Deserialize, Clone, Debug)]
+pub struct DatingScene {
+    pub id: String,
+    // A name or alias from the roster
+    pub person: Option<String>,
+    pub text: Vec<DialogueLine>,
+    pub outcome: Option<Vec<(String, isize)>>,
+    #[serde(default)]
+    pub choice: Vec<(String, String)>,
+    #[serde(default)]
+    pub next: Vec<String>,
+    pub mission: Option<MissionType>,
+    pub requires: Option<Condition>,
+}
+
+impl DatingScene {
+    // The day in ids like "Day2Evening" or "TakeStockDay1"
+    pub fn day(&self) -> Option<usize> {
+        let (_, rest) = self.id.split_once("Day")?;
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        rest[..digits].parse().ok()
+    }
+
+    // Scenes started by talking to someone, scheduled for a day, e.g. "Day2Evening",
+    // alerting about a ship store, e.g. "OxygenLow", or the ending
+    pub fn is_entry(&self) -> bool {
+        let day_scene = self.id.strip_prefix("Day").is_some_and(|rest| {
+            let phase = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+            phase.len() < rest.len() && (phase == "Morning" || phase == "Evening")
+        });
+        let alert = Supply::ALL
+            .iter()
+            .any(|supply| supply.alert_scene() == self.id);
+        self.person.is_some() || day_scene || alert || self.id == ENDING_SCENE
+    }
+}
+
+/// A line of dialogue, written either as `"narration"` or `[speaker, "line"]`
+/// where a `null` speaker is narration as well. `[speaker, "line", "expression"]`
+/// shows the speaker's portrait for that expression.
+#[derive(Clone, Debug)]
+pub struct DialogueLine {
+    pub speaker: Option<String>,
+    pub line: String,
+    pub expression: Option<String>,
+}
+
+impl DialogueLine {
+    pub fn narration(line: &str) -> DialogueLine {
+        DialogueLine {
+            speaker: None,
+            line: line.to_string(),
+            expression: None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DialogueLine {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LineVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for LineVisitor {
+            type Value = DialogueLine;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a line of text or a [speaker, line, expression] list")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, line: &str) -> Result<DialogueLine, E> {
+                Ok(DialogueLine::narration(line))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<DialogueLine, A::Error> {
+                use serde::de::Error;
+                let speaker = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                let line = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                let expression = seq.next_element()?.flatten();
+                if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                    return Err(A::Error::invalid_length(4, &self));
+                }
+                Ok(DialogueLine {
+                    speaker,
+                    line,
+                    expression,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(LineVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::read_scenes;
+    use crate::roster::read_roster;
+    use std::path::Path;
+
+    fn test_context() -> DatingContext {
+        let scenes = read_scenes(Path::new("assets/Scenes")).unwrap();
+        let roster = read_roster(Path::new("assets/characters.ron")).unwrap();
+        DatingContext::new(scenes, Arc::new(roster))
+    }
+
+    fn status(context: &DatingContext, name: &str) -> (usize, bool) {
+        let id = context.find_character(name).unwrap();
+        let status = context.character(&id).unwrap();
+        (status.favor, status.alive)
+    }
+
+    #[test]
+    fn eating_the_cat_kills_the_cat_and_lowers_fredricks_favor() {
+        let mut context = test_context();
+        let food = context.ship.get(Supply::Food);
+        let step = context.simulate(&[Input::Play("Should we eat".to_string()), Input::Skip]);
+        assert_eq!(step, Ok(Step::Choosing));
+
+        assert_eq!(
+            context.apply(Step::Choosing, &Input::Choose(0)),
+            Some(Step::Line)
+        );
+        assert_eq!(context.apply(Step::Line, &Input::Advance), Some(Step::Line));
+        assert_eq!(
+            context.flag("CatDead"),
+            0,
+            "outcome waits for the scene to end"
+        );
+
+        assert_eq!(
+            context.apply(Step::Line, &Input::Advance),
+            Some(Step::Finished)
+        );
+        assert_eq!(context.flag("CatDead"), 1);
+        assert_eq!(status(&context, "Cat"), (20, false));
+        assert_eq!(status(&context, "Fredrick"), (15, true));
+        assert_eq!(context.ship.get(Supply::Food), food + 3);
+        assert!(context.gathered_mission.is_empty());
+    }
+
+    #[test]
+    fn sparing_the_cat_sends_the_captain_for_oil() {
+        let mut context = test_context();
+        let food = context.ship.get(Supply::Food);
+        let step = context.simulate(&[
+            Input::Play("Should we eat".to_string()),
+            Input::Skip,
+            Input::Choose(1),
+            Input::Skip,
+        ]);
+        assert_eq!(step, Ok(Step::Finished));
+        assert_eq!(context.flag("CatDead"), 0);
+        assert_eq!(status(&context, "Cat"), (20, true));
+        assert_eq!(status(&context, "Fredrick"), (20, true));
+        assert_eq!(context.ship.get(Supply::Food), food);
+        let missions: Vec<_> = context.gathered_mission.iter().map(|m| m.kind).collect();
+        assert_eq!(missions, [MissionType::Oil]);
+    }
+
+    #[test]
+    fn only_scenes_picked_by_talking_can_be_left() {
+        let mut context = test_context();
+        let joe = context.find_character("Joe").unwrap();
+        assert!(context.talk_to(&joe));
+        assert!(context.can_leave);
+
+        let step = context.simulate(&[Input::Play("Should we eat".to_string()), Input::Skip]);
+        assert_eq!(step, Ok(Step::Choosing));
+        assert!(!context.can_leave);
+        assert_eq!(
+            context.apply(Step::Choosing, &Input::Choose(1)),
+            Some(Step::Line)
+        );
+        assert!(!context.can_leave);
+    }
+
+    #[test]
+    fn dead_crew_have_nothing_to_say() {
+        let mut context = test_context();
+        let joe = context.find_character("Joe").unwrap();
+        context.apply_outcome(&[("JoeDead".to_string(), 1)]);
+        assert!(!context.talk_to(&joe));
+        assert!(context.alive_crew().all(|status| status.character != joe));
+    }
+
+    #[test]
+    fn history_logs_each_line_read_once() {
+        let mut context = test_context();
+        // Left on the first line and started again
+        context
+            .simulate(&[Input::Play("Should we eat".to_string())])
+            .unwrap();
+        let step = context.simulate(&[
+            Input::Play("Should we eat".to_string()),
+            Input::Skip,
+            Input::Choose(1),
+            Input::Skip,
+        ]);
+        assert_eq!(step, Ok(Step::Finished));
+        let history: Vec<_> = context
+            .history
+            .iter()
+            .map(|logged| (logged.text.as_str(), logged.choice))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                ("I am hungry", false),
+                ("That cat is looking really yummy", false),
+                ("We should not eat the cat", true),
+                ("Cringe", false),
+                (
+                    "Go out onto the planet and find some food then idiot",
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_dead_cat_cant_be_eaten_again() {
+        let mut context = test_context();
+        context.apply_outcome(&[("CatDead".to_string(), 1)]);
+        let step = context.simulate(&[Input::Play("Should we eat".to_string()), Input::Skip]);
+        assert_eq!(step, Ok(Step::Choosing));
+        assert_eq!(
+            context.choices(),
+            [
+                ("We should eat the cat".to_string(), false),
+                ("We should not eat the cat".to_string(), true),
+            ]
+        );
+        assert!(context.simulate(&[Input::Choose(0)]).is_err());
+    }
+}
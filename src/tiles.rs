@@ -0,0 +1,123 @@
+//! What each kind of tile is like, read from `assets/tiles.json`:
+//!
+//! ```json
+//! {
+//!   "tiles": [
+//!     { "tile": "Air", "color": "#FFFFFF" },
+//!     { "tile": "Ice", "color": "#0000FF", "solid": true, "hardness": 1.5,
+//!       "drop": "Water", "sprite": "Map/Ice.png", "friction": 0.05 }
+//!   ]
+//! }
+//! ```
+//!
+//! `color` is the pixel color that stands for the tile in map images, every tile
+//! needs one and no two tiles can share it. `hardness` is how many seconds it takes
+//! to dig through, `drop` is what digging it gives. Tiles without a `sprite` are
+//! drawn in their color.
+
+use crate::story::MissionType;
+use anyhow::bail;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const TILES_PATH: &str = "tiles.json";
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Rock,
+    Ice,
+    Oil,
+    Iron,
+    Air,
+}
+
+impl Tile {
+    pub const ALL: [Tile; 5] = [Tile::Rock, Tile::Ice, Tile::Oil, Tile::Iron, Tile::Air];
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileInfo {
+    pub tile: Tile,
+    pub color: ColorKey,
+    #[serde(default)]
+    pub solid: bool,
+    #[serde(default)]
+    pub hardness: f32,
+    pub drop: Option<MissionType>,
+    pub sprite: Option<String>,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+}
+
+fn default_friction() -> f32 {
+    0.5
+}
+
+/// An rgb color written as `#RRGGBB`.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String")]
+pub struct ColorKey(pub [u8; 3]);
+
+impl TryFrom<String> for ColorKey {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        match u32::from_str_radix(digits, 16) {
+            Ok(rgb) if digits.len() == 6 => {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                Ok(ColorKey([r, g, b]))
+            }
+            _ => Err(format!("expected a #RRGGBB color, found `{hex}`")),
+        }
+    }
+}
+
+impl std::fmt::Display for ColorKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+#[derive(Deserialize)]
+struct TileFile {
+    tiles: Vec<TileInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TileRegistry {
+    tiles: HashMap<Tile, TileInfo>,
+    by_color: HashMap<ColorKey, Tile>,
+}
+
+impl TileRegistry {
+    pub fn parse(json: &str) -> anyhow::Result<TileRegistry> {
+        let file: TileFile = serde_json::from_str(json)?;
+        let mut tiles = HashMap::new();
+        let mut by_color = HashMap::new();
+
+        for info in file.tiles {
+            if tiles.contains_key(&info.tile) {
+                bail!("{:?} is described twice", info.tile);
+            }
+            if let Some(other) = by_color.insert(info.color, info.tile) {
+                bail!("{:?} and {other:?} both use {}", info.tile, info.color);
+            }
+            tiles.insert(info.tile, info);
+        }
+        if let Some(missing) = Tile::ALL.iter().find(|tile| !tiles.contains_key(tile)) {
+            bail!("{missing:?} is missing");
+        }
+
+        Ok(TileRegistry { tiles, by_color })
+    }
+
+    pub fn get(&self, tile: Tile) -> &TileInfo {
+        &self.tiles[&tile]
+    }
+
+    pub fn tile_with_color(&self, color: [u8; 3]) -> Option<Tile> {
+        self.by_color.get(&ColorKey(color)).copied()
+    }
+}
@@ -0,0 +1,152 @@
+//! Reveals a line of dialogue a character at a time. Markup in the line changes how
+//! the rest of it shows and isn't shown itself:
+//!
+//! - `{pause 0.5}` waits half a second
+//! - `{speed 2}` shows the rest twice as fast, `{speed 1}` goes back to normal
+//!
+//! e.g. `"Wait...{pause 1} is that{speed 0.3} oil?"`
+
+use bevy::prelude::*;
+
+pub const CHARS_PER_SECOND: f32 = 40.0;
+// Auto mode reads a line for this long plus a bit for every character
+const AUTO_DELAY: f32 = 1.0;
+const AUTO_DELAY_PER_CHAR: f32 = 0.04;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mark {
+    Pause(f32),
+    Speed(f32),
+}
+
+/// Splits a line into its text and the markup in front of each character index.
+pub fn parse(line: &str) -> Result<(String, Vec<(usize, Mark)>), String> {
+    let mut text = String::new();
+    let mut marks = vec![];
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err(format!("unclosed {{ in `{line}`"));
+        };
+        let markup = &rest[start + 1..start + len];
+        let (name, value) = markup.split_once(' ').unwrap_or((markup, ""));
+        let value = value
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|value| *value >= 0.0);
+        let mark = match (name, value) {
+            ("pause", Some(seconds)) => Mark::Pause(seconds),
+            ("speed", Some(speed)) if speed > 0.0 => Mark::Speed(speed),
+            _ => return Err(format!("bad markup {{{markup}}} in `{line}`")),
+        };
+        marks.push((text.chars().count(), mark));
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(rest);
+    Ok((text, marks))
+}
+
+/// How conversations play out, auto and skip are toggled while talking.
+#[derive(Resource, Debug)]
+pub struct TextSettings {
+    pub chars_per_second: f32,
+    /// Moves on by itself once a line had time to be read
+    pub auto: bool,
+    /// Rushes through lines that were read before, stops at the first new one
+    pub skip: bool,
+}
+
+impl Default for TextSettings {
+    fn default() -> Self {
+        TextSettings {
+            chars_per_second: CHARS_PER_SECOND,
+            auto: false,
+            skip: false,
+        }
+    }
+}
+
+/// The line being revealed in the text box.
+#[derive(Resource, Default, Debug)]
+pub struct Typewriter {
+    text: String,
+    marks: Vec<(usize, Mark)>,
+    // Characters showing and the first mark not applied yet
+    shown: usize,
+    next_mark: usize,
+    speed: f32,
+    // Seconds until the next character
+    wait: f32,
+    /// Seconds since the whole line showed
+    pub idle: f32,
+}
+
+impl Typewriter {
+    /// Starts revealing a line, markup that can't be read is shown as it is.
+    pub fn start(&mut self, line: &str) {
+        let (text, marks) = parse(line).unwrap_or_else(|err| {
+            warn!("{err}");
+            (line.to_string(), vec![])
+        });
+        *self = Typewriter {
+            text,
+            marks,
+            speed: 1.0,
+            ..default()
+        };
+    }
+
+    pub fn tick(&mut self, seconds: f32, chars_per_second: f32) {
+        if self.is_done() {
+            self.idle += seconds;
+            return;
+        }
+        self.wait -= seconds;
+        while self.wait <= 0.0 && !self.is_done() {
+            while let Some((_, mark)) = self
+                .marks
+                .get(self.next_mark)
+                .filter(|(at, _)| *at == self.shown)
+            {
+                match mark {
+                    Mark::Pause(seconds) => self.wait += seconds,
+                    Mark::Speed(speed) => self.speed = *speed,
+                }
+                self.next_mark += 1;
+            }
+            if self.wait > 0.0 {
+                break;
+            }
+            self.shown += 1;
+            self.wait += 1.0 / (chars_per_second * self.speed);
+        }
+    }
+
+    /// Shows the whole line at once.
+    pub fn complete(&mut self) {
+        if !self.is_done() {
+            self.shown = self.text.chars().count();
+            self.next_mark = self.marks.len();
+            self.idle = 0.0;
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.shown >= self.text.chars().count()
+    }
+
+    /// The part of the line showing so far.
+    pub fn visible(&self) -> &str {
+        match self.text.char_indices().nth(self.shown) {
+            Some((end, _)) => &self.text[..end],
+            None => &self.text,
+        }
+    }
+
+    /// How long auto mode waits after the line showed.
+    pub fn auto_delay(&self) -> f32 {
+        AUTO_DELAY + AUTO_DELAY_PER_CHAR * self.text.chars().count() as f32
+    }
+}
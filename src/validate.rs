@@ -0,0 +1,392 @@
+//! `cargo run -- --validate` checks the character roster and every scene file
+//! without starting the game.
+
+use crate::condition::Value as ConditionValue;
+use crate::load::{parse_scenes, scene_files};
+use crate::roster::Roster;
+use crate::ship::Supply;
+use crate::story::{self, DatingScene, MissionType};
+use crate::typewriter;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+struct SceneSource {
+    path: PathBuf,
+    json: String,
+    scene: DatingScene,
+}
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn problem(&mut self, path: &Path, line: usize, message: String) {
+        self.problems += 1;
+        println!("{}:{line}: {message}", path.display());
+    }
+}
+
+/// Prints every problem found and returns the process exit code.
+pub fn run(scene_dir: &Path, roster_path: &Path) -> i32 {
+    let mut report = Report::default();
+
+    let Some(roster) = check_roster(roster_path, &mut report) else {
+        println!("Fix the roster first, scenes can't be checked without it");
+        return 1;
+    };
+
+    let paths = match scene_files(scene_dir) {
+        Ok(paths) => paths,
+        Err(err) => {
+            println!("{}: {err}", scene_dir.display());
+            return 1;
+        }
+    };
+
+    let mut sources = Vec::new();
+    // Scenes in files that don't parse, links to them aren't missing
+    let mut unparsed = HashSet::new();
+    for path in paths {
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) => {
+                report.problem(&path, 0, err.to_string());
+                continue;
+            }
+        };
+
+        // Name checks run on the raw json so every bad name is reported,
+        // not just the first one the parser trips over
+        let bad_missions = check_names(&path, &json, &roster, &mut report);
+        match parse_scenes(&json) {
+            Ok(scenes) => sources.extend(scenes.into_iter().map(|scene| SceneSource {
+                path: path.clone(),
+                json: json.clone(),
+                scene,
+            })),
+            Err(err) => {
+                let message = format!("{err:#}");
+                let reported = bad_missions
+                    .iter()
+                    .any(|mission| message.contains(&format!("unknown variant `{mission}`")));
+                if !reported {
+                    let line = err
+                        .downcast_ref::<serde_json::Error>()
+                        .map_or(0, |err| err.line());
+                    report.problem(&path, line, message);
+                }
+                unparsed.extend(raw_scenes(&json).iter().filter_map(|scene| {
+                    scene.get("id").and_then(Value::as_str).map(str::to_string)
+                }));
+            }
+        }
+    }
+
+    check_graph(&sources, &unparsed, &roster, &mut report);
+
+    if report.problems == 0 {
+        println!("All scenes are fine");
+        0
+    } else {
+        println!("{} problem(s) found", report.problems);
+        1
+    }
+}
+
+fn check_roster(path: &Path, report: &mut Report) -> Option<Roster> {
+    let ron = match std::fs::read_to_string(path) {
+        Ok(ron) => ron,
+        Err(err) => {
+            report.problem(path, 0, err.to_string());
+            return None;
+        }
+    };
+    let roster = match Roster::parse(&ron) {
+        Ok(roster) => roster,
+        Err(err) => {
+            let line = err
+                .downcast_ref::<ron::error::SpannedError>()
+                .map_or(0, |err| err.position.line);
+            report.problem(path, line, format!("{err:#}"));
+            return None;
+        }
+    };
+
+    // Portraits are relative to the assets folder the roster is in
+    let assets = path.parent().unwrap_or(Path::new("."));
+    for info in roster.characters() {
+        let mut portraits: Vec<_> = info.portraits.values().collect();
+        portraits.sort();
+        for portrait in portraits {
+            if !assets.join(portrait).is_file() {
+                let line = line_of(&ron, portrait, 0);
+                report.problem(
+                    path,
+                    line,
+                    format!("{}: missing portrait {portrait}", info.id),
+                );
+            }
+        }
+    }
+    Some(roster)
+}
+
+// The scenes of a file as plain json, empty if it isn't a scene file at all
+fn raw_scenes(json: &str) -> Vec<Value> {
+    match serde_json::from_str::<Value>(json) {
+        Ok(Value::Array(scenes)) => scenes,
+        Ok(mut file) => match file.get_mut("scenes").map(Value::take) {
+            Some(Value::Array(scenes)) => scenes,
+            _ => vec![],
+        },
+        Err(_) => vec![],
+    }
+}
+
+// Returns the unknown missions it reported, parsing the file fails on those too
+fn check_names(path: &Path, json: &str, roster: &Roster, report: &mut Report) -> Vec<String> {
+    let mut bad_missions = vec![];
+    for scene in &raw_scenes(json) {
+        let id = scene.get("id").and_then(Value::as_str).unwrap_or("?");
+        let scene_line = scene_line(json, id);
+
+        if let Some(person) = scene.get("person").and_then(Value::as_str) {
+            if roster.find(person).is_none() {
+                let line = line_of(json, person, scene_line);
+                report.problem(
+                    path,
+                    line,
+                    format!("scene \"{id}\": unknown person {person}"),
+                );
+            }
+        }
+
+        if let Some(mission) = scene.get("mission").and_then(Value::as_str) {
+            if MissionType::from_name(mission).is_none() {
+                let line = line_of(json, mission, scene_line);
+                report.problem(
+                    path,
+                    line,
+                    format!("scene \"{id}\": unknown mission {mission}"),
+                );
+                bad_missions.push(mission.to_string());
+            }
+        }
+
+        let lines = scene.get("text").and_then(Value::as_array);
+        for line in lines.into_iter().flatten() {
+            let text = line
+                .as_str()
+                .or_else(|| line.get(1).and_then(Value::as_str));
+            if let Some(text) = text {
+                let captain = roster.player().map_or("", |info| info.name.as_str());
+                if let Err(problem) = typewriter::parse(&text.replace("{captain}", captain)) {
+                    let line = line_of(json, text, scene_line);
+                    report.problem(path, line, format!("scene \"{id}\": {problem}"));
+                }
+            }
+
+            let Some(speaker) = line.get(0).and_then(Value::as_str) else {
+                continue;
+            };
+            let Some(character) = roster.find(speaker).and_then(|id| roster.get(id)) else {
+                let line = line_of(json, speaker, scene_line);
+                report.problem(
+                    path,
+                    line,
+                    format!("scene \"{id}\": unknown speaker {speaker}"),
+                );
+                continue;
+            };
+            if let Some(expression) = line.get(2).and_then(Value::as_str) {
+                if !character.portraits.contains_key(expression) {
+                    let line = line_of(json, expression, scene_line);
+                    let problem = format!("{speaker} has no {expression} portrait");
+                    report.problem(path, line, format!("scene \"{id}\": {problem}"));
+                }
+            }
+        }
+    }
+
+    bad_missions
+}
+
+fn check_graph(
+    sources: &[SceneSource],
+    unparsed: &HashSet<String>,
+    roster: &Roster,
+    report: &mut Report,
+) {
+    let mut by_id: HashMap<&str, &SceneSource> = HashMap::new();
+    for source in sources {
+        let scene = &source.scene;
+        if let Some(first) = by_id.insert(&scene.id, source) {
+            report.problem(
+                &source.path,
+                source.line(),
+                format!(
+                    "scene \"{}\" is also defined in {}:{}",
+                    scene.id,
+                    first.path.display(),
+                    first.line()
+                ),
+            );
+        }
+    }
+
+    let links = |scene: &DatingScene| -> Vec<String> {
+        let choices = scene.choice.iter().map(|(_, target)| target.clone());
+        choices.chain(scene.next.iter().cloned()).collect()
+    };
+
+    let game_flags = story::game_flags();
+    let mut written: HashSet<&str> = game_flags.iter().map(String::as_str).collect();
+    for source in sources {
+        let scene = &source.scene;
+        for target in links(scene) {
+            if !by_id.contains_key(target.as_str()) && !unparsed.contains(&target) {
+                let line = line_of(&source.json, &target, source.line());
+                report.problem(
+                    &source.path,
+                    line,
+                    format!("scene \"{}\" links to missing scene \"{target}\"", scene.id),
+                );
+            }
+        }
+        for (flag, _) in scene.outcome.iter().flatten() {
+            if !flag.ends_with("Favor") && !flag.ends_with("supply") {
+                written.insert(flag.as_str());
+            }
+        }
+    }
+
+    for source in sources {
+        let scene = &source.scene;
+        let values = scene
+            .requires
+            .iter()
+            .flat_map(|condition| condition.values());
+        for value in values {
+            let problem = match value {
+                ConditionValue::Flag(flag) if !written.contains(flag.as_str()) => {
+                    format!("flag {flag} is read but no outcome sets it")
+                }
+                ConditionValue::Favor(name) | ConditionValue::Alive(name)
+                    if roster.find(name).is_none() =>
+                {
+                    format!("unknown character {name} in requires")
+                }
+                ConditionValue::Mission(name) if MissionType::from_name(name).is_none() => {
+                    format!("unknown mission {name} in requires")
+                }
+                ConditionValue::Supply(name) if Supply::from_name(name).is_none() => {
+                    format!("unknown supply {name} in requires")
+                }
+                _ => continue,
+            };
+            report.problem(
+                &source.path,
+                source.line(),
+                format!("scene \"{}\": {problem}", scene.id),
+            );
+        }
+    }
+
+    // Without every scene these would only be guesses
+    if !unparsed.is_empty() {
+        return;
+    }
+
+    // Reachable from a scene the game starts on its own
+    let mut reachable = HashSet::new();
+    let mut queue: Vec<&str> = sources
+        .iter()
+        .filter(|source| source.scene.is_entry())
+        .map(|source| source.scene.id.as_str())
+        .collect();
+    while let Some(id) = queue.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+        if let Some(source) = by_id.get(id) {
+            for target in &source.scene.choice {
+                queue.push(&target.1);
+            }
+            queue.extend(source.scene.next.iter().map(String::as_str));
+        }
+    }
+
+    // Able to reach a scene that ends the conversation
+    let mut can_end: HashSet<&str> = sources
+        .iter()
+        .filter(|source| source.scene.choice.is_empty() && source.scene.next.is_empty())
+        .map(|source| source.scene.id.as_str())
+        .collect();
+    loop {
+        let before = can_end.len();
+        for source in sources {
+            let scene = &source.scene;
+            if links(scene)
+                .iter()
+                .any(|target| can_end.contains(target.as_str()))
+            {
+                can_end.insert(&scene.id);
+            }
+        }
+        if can_end.len() == before {
+            break;
+        }
+    }
+
+    for source in sources {
+        let id = source.scene.id.as_str();
+        if !reachable.contains(id) {
+            report.problem(
+                &source.path,
+                source.line(),
+                format!("scene \"{id}\" can never be reached"),
+            );
+        }
+        if !can_end.contains(id) {
+            report.problem(
+                &source.path,
+                source.line(),
+                format!("scene \"{id}\" is stuck in a loop that never ends"),
+            );
+        }
+    }
+}
+
+impl SceneSource {
+    fn line(&self) -> usize {
+        scene_line(&self.json, &self.scene.id)
+    }
+}
+
+// Line of `"id": "<id>"`
+fn scene_line(json: &str, id: &str) -> usize {
+    let quoted = format!("\"{id}\"");
+    json.lines()
+        .position(|line| {
+            line.match_indices("\"id\"").any(|(idx, key)| {
+                let rest = line[idx + key.len()..].trim_start();
+                rest.strip_prefix(':')
+                    .is_some_and(|value| value.trim_start().starts_with(&quoted))
+            })
+        })
+        .map_or(0, |idx| idx + 1)
+}
+
+// Line of the first quoted `needle` at or after line `from`, good enough to point writers
+// at the right place
+fn line_of(json: &str, needle: &str, from: usize) -> usize {
+    let quoted = format!("\"{needle}\"");
+    json.lines()
+        .enumerate()
+        .skip(from.saturating_sub(1))
+        .find(|(_, line)| line.contains(&quoted))
+        .map_or(from, |(idx, _)| idx + 1)
+}